@@ -1,39 +1,52 @@
 use url::Url;
-use hyper::Client;
 use hyper::header::{Authorization, Basic, ContentType, Headers, UserAgent};
+use hyper::method::Method;
 use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
 use hyper::status::StatusCode;
 use rand::{Rng, thread_rng};
 use serde_json;
 use base64;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
 use config::{Config, CommandLineOptions};
 
 use std::collections::BTreeMap;
 use std::io::Read;
 use std::sync::Arc;
 
+const DEFAULT_SERVER: &'static str = "https://bitbucket.org";
+const DEFAULT_API_SERVER: &'static str = "https://api.bitbucket.org";
+
 pub struct Bitbucket {
   config: Arc<Config>,
   cli: Arc<CommandLineOptions>,
-  client: Client
+  client: HttpClient,
+  server: String,
+  api_server: String,
+  host: String
 }
 
 impl Bitbucket {
   pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Bitbucket {
+    let server = super::server(&config.bitbucket.server, DEFAULT_SERVER);
+    let api_server = super::server(&config.bitbucket.api_server, DEFAULT_API_SERVER);
+    let host = super::host(&server);
     Bitbucket {
       config: config,
       cli: cli,
-      client: ::new_client()
+      client: ::transport::new_client(),
+      server: server,
+      api_server: api_server,
+      host: host
     }
   }
 
   fn get_snippet(&self, id: &str) -> Result<Snippet> {
-    let url_str = format!("https://bitbucket.org/snippets/{}", id);
+    let url_str = format!("{}/snippets/{}", self.server, id);
     let url = Url::parse(&url_str)?;
     let segments: Vec<_> = match url.path_segments() {
       Some(p) => p.collect(),
@@ -45,9 +58,9 @@ impl Bitbucket {
     let username = segments[1];
     let id = segments[2];
 
-    let api_url = Url::parse(&format!("https://api.bitbucket.org/2.0/snippets/{}/{}", username, id))?;
+    let api_url = Url::parse(&format!("{}/2.0/snippets/{}/{}", self.api_server, username, id))?;
     let mut res = self.client.get(api_url)
-      .header(UserAgent(format!("bins/{}", crate_version!())))
+      .header(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))))
       .header(self.authorization()?)
       .send()?;
     let mut content = String::new();
@@ -86,7 +99,7 @@ impl Bitbucket {
 
   fn prepare_body(&self, data: &[UploadFile], boundary: &str) -> Result<String> {
     let properties = SnippetProperties {
-      title: "bins".to_string(),
+      title: self.cli.title.clone().unwrap_or_else(|| "bins".to_string()),
       is_private: self.cli.private.unwrap_or_default()
     };
     let properties_json = serde_json::to_string(&properties)?;
@@ -94,11 +107,36 @@ impl Bitbucket {
     let mut body = MultipartRelatedBody::new(boundary);
     body.add_json(&properties_json);
     for file in data {
-      body.add_file(&file.name, file.content.as_bytes());
+      body.add_file(&file.name, &file.content, file.is_binary());
     }
 
     Ok(body.end())
   }
+
+  fn send_snippet(&self, method: Method, url: &str, contents: &[UploadFile], expected: StatusCode) -> Result<Vec<PasteUrl>> {
+    let authorization = self.authorization()?;
+
+    let boundary = self.random_boundary();
+    let headers = self.prepare_headers(&boundary, authorization);
+    let body = self.prepare_body(contents, &boundary)?;
+
+    let mut response = self.client.request(method, url)
+      .headers(headers)
+      .body(&body)
+      .send()?;
+
+    let mut response_body = String::new();
+    response.read_to_string(&mut response_body)?;
+    if response.status != expected {
+      return Err(ErrorKind::BinError(response_body).into());
+    }
+
+    let snippet: serde_json::Value = serde_json::from_str(&response_body)?;
+    match snippet.pointer("/links/html/href") {
+      Some(h) if h.is_string() => Ok(vec![PasteUrl::html(None, h.as_str().unwrap().to_string())]),
+      _ => Err(ErrorKind::InvalidResponse.into())
+    }
+  }
 }
 
 impl Bin for Bitbucket {
@@ -107,11 +145,11 @@ impl Bin for Bitbucket {
   }
 
   fn html_host(&self) -> &str {
-    "bitbucket.org"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "bitbucket.org"
+    &self.host
   }
 }
 
@@ -154,6 +192,9 @@ impl CreatesHtmlUrls for Bitbucket {
     url.set_query(None);
     let segments: Vec<_> = option!(url.path_segments()).collect();
     let size = segments.len();
+    if size < 2 {
+      return None;
+    }
     Some(format!("{}/{}", segments[size - 2], segments[size - 1]))
   }
 }
@@ -183,7 +224,7 @@ impl CreatesRawUrls for Bitbucket {
         return self.id_from_html_url(str_url);
       }
     }
-    Some(format!("{}/{}", segments[3], segments[4]))
+    Some(format!("{}/{}", option!(segments.get(3)), option!(segments.get(4))))
   }
 }
 
@@ -193,41 +234,51 @@ impl HasFeatures for Bitbucket {
          BinFeature::Private,
          BinFeature::Authed,
          BinFeature::MultiFile,
-         BinFeature::SingleNaming]
+         BinFeature::SingleNaming,
+         BinFeature::Deletable,
+         BinFeature::Updatable,
+         BinFeature::BinaryFiles,
+         BinFeature::Titled]
   }
 }
 
 impl Uploads for Bitbucket {
   fn upload(&self, contents: &[UploadFile], _: bool) -> Result<Vec<PasteUrl>> {
-    let authorization = self.authorization()?;
+    self.send_snippet(Method::Post, &format!("{}/2.0/snippets", self.api_server), contents, StatusCode::Created)
+  }
+}
 
-    let boundary = self.random_boundary();
-    let headers = self.prepare_headers(&boundary, authorization);
-    let body = self.prepare_body(contents, &boundary)?;
+impl Updates for Bitbucket {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let url = format!("{}/2.0/snippets/{}", self.api_server, id);
+    self.send_snippet(Method::Put, &url, contents, StatusCode::Ok)
+  }
+}
 
-    let mut response = self.client.post("https://api.bitbucket.org/2.0/snippets")
-      .headers(headers)
-      .body(&body)
+impl Deletes for Bitbucket {
+  fn delete(&self, id: &str) -> Result<()> {
+    let api_url = Url::parse(&format!("{}/2.0/snippets/{}", self.api_server, id))?;
+    let mut res = self.client.delete(api_url)
+      .header(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))))
+      .header(self.authorization()?)
       .send()?;
-
-    let mut response_body = String::new();
-    response.read_to_string(&mut response_body)?;
-    if response.status != StatusCode::Created {
-      return Err(ErrorKind::BinError(response_body).into());
-    }
-
-    let snippet: serde_json::Value = serde_json::from_str(&response_body)?;
-    match snippet.pointer("/links/html/href") {
-      Some(h) if h.is_string() => Ok(vec![PasteUrl::html(None, h.as_str().unwrap().to_string())]),
-      _ => Err(ErrorKind::InvalidResponse.into())
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status != StatusCode::NoContent {
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
     }
+    Ok(())
   }
 }
 
 impl HasClient for Bitbucket {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
 
 #[derive(Deserialize)]
@@ -279,9 +330,13 @@ impl<'a> MultipartRelatedBody<'a> {
     self.end_line();
   }
 
-  fn add_file(&mut self, filename: &str, content: &[u8]) {
+  fn add_file(&mut self, filename: &str, content: &[u8], binary: bool) {
     self.add_boundary();
-    self.add_line("Content-Type: text/plain; charset=\"utf-8\"");
+    if binary {
+      self.add_line("Content-Type: application/octet-stream");
+    } else {
+      self.add_line("Content-Type: text/plain; charset=\"utf-8\"");
+    }
     self.add_line("MIME-Version: 1.0");
     self.add_line("Content-Transfer-Encoding: base64");
 
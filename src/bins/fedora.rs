@@ -1,22 +1,34 @@
 use url::Url;
-use hyper::Client;
 use serde_json;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::{Config, CommandLineOptions};
 
 use std::io::Read;
+use std::sync::Arc;
+
+const DEFAULT_SERVER: &'static str = "https://paste.fedoraproject.org";
 
 pub struct Fedora {
-  client: Client
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  server: String,
+  host: String
 }
 
 impl Fedora {
-  pub fn new() -> Fedora {
+  pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Fedora {
+    let server = super::server(&config.fedora.server, DEFAULT_SERVER);
+    let host = super::host(&server);
     Fedora {
-      client: ::new_client()
+      cli: cli,
+      client: ::transport::new_client(),
+      server: server,
+      host: host
     }
   }
 
@@ -34,11 +46,11 @@ impl Bin for Fedora {
   }
 
   fn html_host(&self) -> &str {
-    "paste.fedoraproject.org"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "paste.fedoraproject.org"
+    &self.host
   }
 }
 
@@ -50,13 +62,13 @@ impl FormatsUrls for Fedora {}
 
 impl FormatsHtmlUrls for Fedora {
   fn format_html_url(&self, id: &str) -> Option<String> {
-    Some(format!("https://paste.fedoraproject.org/paste/{}", id))
+    Some(format!("{}/paste/{}", self.server, id))
   }
 }
 
 impl FormatsRawUrls for Fedora {
   fn format_raw_url(&self, id: &str) -> Option<String> {
-    Some(format!("https://paste.fedoraproject.org/paste/{}/raw", id))
+    Some(format!("{}/paste/{}/raw", self.server, id))
   }
 }
 
@@ -64,29 +76,7 @@ impl CreatesHtmlUrls for Fedora {
   fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     let html_url = self.format_html_url(id).unwrap();
     let raw_url = self.format_raw_url(id).unwrap();
-    let mut res = self.client.get(&raw_url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self.format_html_url(&id).unwrap())).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(html_url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &raw_url, &html_url, |id| self.format_html_url(id).unwrap())
   }
 
   fn id_from_html_url(&self, url: &str) -> Option<String> {
@@ -98,29 +88,7 @@ impl CreatesRawUrls for Fedora {
   fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     debug!("creating raw url for {}", id);
     let raw_url = self.format_raw_url(id).unwrap();
-    let mut res = self.client.get(&raw_url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self.format_raw_url(&id).unwrap())).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(raw_url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &raw_url, &raw_url, |id| self.format_raw_url(id).unwrap())
   }
 
   fn id_from_raw_url(&self, url: &str) -> Option<String> {
@@ -132,7 +100,9 @@ impl HasFeatures for Fedora {
   fn features(&self) -> Vec<BinFeature> {
     vec![BinFeature::Public,
          BinFeature::Anonymous,
-         BinFeature::SingleNaming]
+         BinFeature::SingleNaming,
+         BinFeature::Expiring,
+         BinFeature::Titled]
   }
 }
 
@@ -140,11 +110,13 @@ impl UploadsSingleFiles for Fedora {
   fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
     debug!("uploading single file");
     let params = FedoraParams {
-      contents: file.content.clone(),
-      title: file.name.clone()
+      contents: file.text()?.to_owned(),
+      title: self.cli.title.clone().unwrap_or_else(|| file.name.clone()),
+      language: file.language.clone(),
+      expiry_time: self.cli.expires.map(|secs| ::time::get_time().sec + secs as i64)
     };
     let params_json = serde_json::to_string(&params)?;
-    let mut res = self.client.post("https://paste.fedoraproject.org/api/paste/submit")
+    let mut res = self.client.post(&format!("{}/api/paste/submit", self.server))
       .header(::hyper::header::ContentType::json())
       .body(&params_json)
       .send()?;
@@ -169,16 +141,28 @@ impl UploadsSingleFiles for Fedora {
   }
 }
 
+impl Updates for Fedora {}
+
+impl Deletes for Fedora {}
+
 impl HasClient for Fedora {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
 
 #[derive(Debug, Serialize)]
 struct FedoraParams {
   contents: String,
-  title: String
+  title: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  language: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  expiry_time: Option<i64>
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,160 @@
+use url::Url;
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::Config;
+
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_HOST: &'static str = "termbin.com";
+const DEFAULT_PORT: u16 = 9999;
+const TIMEOUT_SECS: u64 = 30;
+
+/// A bin for fiche servers, like termbin, which take pastes over a plain TCP socket and reply with their URL. Pastes
+/// are still downloaded over HTTP.
+pub struct Fiche {
+  client: HttpClient,
+  address: (String, u16),
+  url: String,
+  host: String
+}
+
+impl Fiche {
+  pub fn new(config: Arc<Config>) -> Fiche {
+    let host = config.fiche.host.clone()
+      .and_then(|x| if x.trim().is_empty() { None } else { Some(x) })
+      .unwrap_or_else(|| DEFAULT_HOST.to_owned());
+    let port = config.fiche.port.unwrap_or(DEFAULT_PORT);
+    // fiche servers can be told to give out urls for a different domain than the one they listen on
+    let url = config.fiche.url.clone()
+      .and_then(|x| if x.trim().is_empty() { None } else { Some(x) })
+      .unwrap_or_else(|| format!("http://{}", host))
+      .trim_right_matches('/')
+      .to_owned();
+    let url_host = Url::parse(&url).ok()
+      .and_then(|u| super::url_host(&u))
+      .unwrap_or_else(|| host.clone());
+    Fiche {
+      client: ::transport::new_client(),
+      address: (host, port),
+      url: url,
+      host: url_host
+    }
+  }
+
+  fn id_from_url(&self, url: &str) -> Option<String> {
+    let url = option!(Url::parse(url).ok());
+    let segments = option!(url.path_segments());
+    segments.last().map(|x| x.to_owned())
+  }
+
+  fn send(&self, content: &[u8]) -> Result<String> {
+    let (ref host, port) = self.address;
+    debug!("connecting to {}:{}", host, port);
+    let mut stream = TcpStream::connect((host.as_str(), port))
+      .chain_err(|| format!("could not connect to {}:{}", host, port))?;
+    let timeout = Some(Duration::from_secs(TIMEOUT_SECS));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    stream.write_all(content).chain_err(|| "could not send paste to fiche server")?;
+    // fiche reads until the client stops sending, so close this side before waiting for the url
+    stream.shutdown(Shutdown::Write)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).chain_err(|| "could not read response from fiche server")?;
+    let response = String::from_utf8_lossy(&response);
+    let url = response.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    debug!("fiche response: {}", url);
+    if Url::parse(url).is_err() {
+      return Err(ErrorKind::BinError(url.to_owned()).into());
+    }
+    Ok(url.to_owned())
+  }
+}
+
+impl Bin for Fiche {
+  fn name(&self) -> &str {
+    "fiche"
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for Fiche {}
+
+impl CreatesUrls for Fiche {}
+
+impl FormatsUrls for Fiche {}
+
+impl FormatsHtmlUrls for Fiche {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    Some(format!("{}/{}", self.url, id))
+  }
+}
+
+impl FormatsRawUrls for Fiche {
+  fn format_raw_url(&self, id: &str) -> Option<String> {
+    self.format_html_url(id)
+  }
+}
+
+impl CreatesHtmlUrls for Fiche {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    Ok(vec![PasteUrl::html(None, self.format_html_url(id).unwrap())])
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl CreatesRawUrls for Fiche {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    debug!("creating raw url for {}", id);
+    let raw_url = self.format_raw_url(id).unwrap();
+    super::download_or_index(self, id, &raw_url, &raw_url, |id| self.format_raw_url(id).unwrap())
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl HasFeatures for Fiche {
+  fn features(&self) -> Vec<BinFeature> {
+    vec![BinFeature::Public, BinFeature::Anonymous]
+  }
+}
+
+impl UploadsSingleFiles for Fiche {
+  fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
+    debug!("uploading single file");
+    let url = self.send(file.text()?.as_bytes())?;
+    Ok(PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), url))
+  }
+}
+
+impl Updates for Fiche {}
+
+impl Deletes for Fiche {}
+
+impl HasClient for Fiche {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
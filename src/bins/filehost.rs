@@ -0,0 +1,269 @@
+use url::Url;
+use hyper::header::{Headers, UserAgent};
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::{Config, CommandLineOptions};
+use super::multipart::FormData;
+
+use std::io::Read;
+use std::sync::Arc;
+
+/// The file hosts that `FileHost` can upload to. Both take a multipart form with the file in its `file` field and
+/// respond with the file's URL, but they differ in how expiry, privacy and deletion work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileHostKind {
+  /// 0x0.st and other servers running the null pointer.
+  NullPointer,
+  /// transfer.sh and other servers running its software.
+  TransferSh
+}
+
+impl FileHostKind {
+  fn default_server(&self) -> &'static str {
+    match *self {
+      FileHostKind::NullPointer => "https://0x0.st",
+      FileHostKind::TransferSh => "https://transfer.sh"
+    }
+  }
+}
+
+/// A bin for file hosts that take uploads as multipart forms.
+pub struct FileHost {
+  name: String,
+  kind: FileHostKind,
+  config: Arc<Config>,
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  server: String,
+  host: String
+}
+
+impl FileHost {
+  pub fn null_pointer(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> FileHost {
+    let server = config.null_pointer.server.clone();
+    FileHost::named("0x0", FileHostKind::NullPointer, &server, config, cli)
+  }
+
+  pub fn transfer_sh(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> FileHost {
+    let server = config.transfer_sh.server.clone();
+    FileHost::named("transfersh", FileHostKind::TransferSh, &server, config, cli)
+  }
+
+  /// Creates a bin with its own name and server, so more than one instance of a host can be used at once.
+  pub fn named(name: &str, kind: FileHostKind, server: &Option<String>, config: Arc<Config>, cli: Arc<CommandLineOptions>) -> FileHost {
+    let server = super::server(server, kind.default_server());
+    let host = super::host(&server);
+    FileHost {
+      name: name.to_owned(),
+      kind: kind,
+      config: config,
+      cli: cli,
+      client: ::transport::new_client(),
+      server: server,
+      host: host
+    }
+  }
+
+  fn id_from_url(&self, url: &str) -> Option<String> {
+    let url = option!(Url::parse(url).ok());
+    let segments: Vec<&str> = option!(url.path_segments()).collect();
+    match self.kind {
+      // secret urls have an extra segment before the file name
+      FileHostKind::NullPointer => Some(segments.join("/")),
+      // urls look like /token/file.txt, or /get/token/file.txt for direct downloads
+      FileHostKind::TransferSh => {
+        let size = segments.len();
+        if size < 2 {
+          return None;
+        }
+        Some(format!("{}/{}", segments[size - 2], segments[size - 1]))
+      }
+    }
+  }
+
+  fn post(&self, url: &str, form: FormData, mut headers: Headers) -> Result<(String, Option<String>)> {
+    headers.set(form.content_type());
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
+    let body = form.finish();
+    let mut res = self.client.post(url)
+      .headers(headers)
+      .body(&body[..])
+      .send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status.class().default_code() != ::hyper::Ok {
+      debug!("bad status code");
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    let header = |name: &str| res.headers.get_raw(name)
+      .and_then(|t| t.get(0))
+      .map(|t| String::from_utf8_lossy(t).into_owned());
+    let token = match self.kind {
+      FileHostKind::NullPointer => header("X-Token"),
+      // the deletion url is the file's url followed by the deletion token
+      FileHostKind::TransferSh => header("X-Url-Delete").and_then(|u| u.rsplit('/').next().map(|x| x.to_owned()))
+    };
+    Ok((content.trim().to_owned(), token))
+  }
+}
+
+impl Bin for FileHost {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for FileHost {}
+
+impl CreatesUrls for FileHost {}
+
+impl FormatsUrls for FileHost {}
+
+impl FormatsHtmlUrls for FileHost {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    Some(format!("{}/{}", self.server, id))
+  }
+}
+
+impl FormatsRawUrls for FileHost {
+  fn format_raw_url(&self, id: &str) -> Option<String> {
+    self.format_html_url(id)
+  }
+}
+
+impl CreatesHtmlUrls for FileHost {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    Ok(vec![PasteUrl::html(None, self.format_html_url(id).unwrap())])
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl CreatesRawUrls for FileHost {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    debug!("creating raw url for {}", id);
+    let raw_url = self.format_raw_url(id).unwrap();
+    super::download_or_index(self, id, &raw_url, &raw_url, |id| self.format_raw_url(id).unwrap())
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl HasFeatures for FileHost {
+  fn features(&self) -> Vec<BinFeature> {
+    match self.kind {
+      FileHostKind::NullPointer => vec![BinFeature::Public,
+                                        BinFeature::Private,
+                                        BinFeature::Anonymous,
+                                        BinFeature::Deletable,
+                                        BinFeature::BinaryFiles,
+                                        BinFeature::Expiring],
+      FileHostKind::TransferSh => vec![BinFeature::Public,
+                                       BinFeature::Anonymous,
+                                       BinFeature::Deletable,
+                                       BinFeature::BinaryFiles,
+                                       BinFeature::Expiring,
+                                       BinFeature::BurnAfterReading]
+    }
+  }
+}
+
+impl UploadsSingleFiles for FileHost {
+  fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
+    debug!("uploading single file");
+    let mut form = FormData::new();
+    form.file("file", &file.name, &file.content);
+    let mut headers = Headers::new();
+    let upload_url = match self.kind {
+      FileHostKind::NullPointer => {
+        if let Some(secs) = self.cli.expires {
+          // 0x0 takes the lifetime in hours
+          let hours = (secs + 3599) / 3600;
+          form.text("expires", &hours.to_string());
+        }
+        // private files get a longer, hard to guess url
+        if self.cli.private.or(self.config.defaults.private).unwrap_or(false) {
+          form.text("secret", "");
+        }
+        self.server.clone()
+      },
+      FileHostKind::TransferSh => {
+        if let Some(secs) = self.cli.expires {
+          // transfer.sh takes the lifetime in days
+          let days = (secs + 86399) / 86400;
+          headers.set_raw("Max-Days", vec![days.to_string().into_bytes()]);
+        }
+        if let Some(true) = self.cli.burn {
+          headers.set_raw("Max-Downloads", vec![b"1".to_vec()]);
+        }
+        format!("{}/", self.server)
+      }
+    };
+    let (url, token) = self.post(&upload_url, form, headers)?;
+    if Url::parse(&url).is_err() {
+      return Err(ErrorKind::BinError(url).into());
+    }
+    let url = PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), url);
+    match token {
+      Some(t) => Ok(url.with_deletion_key(t)),
+      None => Ok(url)
+    }
+  }
+}
+
+impl Updates for FileHost {}
+
+impl Deletes for FileHost {
+  fn delete(&self, id: &str) -> Result<()> {
+    let token = match self.cli.deletion_key {
+      Some(ref k) if !k.is_empty() => k,
+      _ => bail!("the token printed when the file was uploaded must be given with --key to delete files")
+    };
+    match self.kind {
+      FileHostKind::NullPointer => {
+        let mut form = FormData::new();
+        form.text("token", token);
+        form.text("delete", "");
+        self.post(&self.format_html_url(id).unwrap(), form, Headers::new())?;
+      },
+      FileHostKind::TransferSh => {
+        let url = format!("{}/{}/{}", self.server, id, token);
+        let mut res = self.client.delete(&url)
+          .header(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))))
+          .send()?;
+        let mut content = String::new();
+        res.read_to_string(&mut content)?;
+        if res.status.class().default_code() != ::hyper::Ok {
+          return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+impl HasClient for FileHost {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
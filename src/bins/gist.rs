@@ -1,13 +1,12 @@
 use url::Url;
-use hyper::Client;
-use hyper::client::RequestBuilder;
 use hyper::header::{Headers, ContentType, UserAgent, Authorization, Basic};
 use serde_json;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, RequestBuilder};
 use config::{Config, CommandLineOptions};
 
 use std::collections::BTreeMap;
@@ -16,43 +15,68 @@ use std::sync::Arc;
 
 const GOOD_CHARS: &'static str = "abcdefghijklmnopqrstuvwxyz0123456789-_";
 
+const DEFAULT_SERVER: &'static str = "https://gist.github.com";
+const DEFAULT_API_SERVER: &'static str = "https://api.github.com";
+const DEFAULT_RAW_SERVER: &'static str = "https://gist.githubusercontent.com";
+
 pub struct Gist {
   config: Arc<Config>,
   cli: Arc<CommandLineOptions>,
-  client: Client
+  client: HttpClient,
+  server: String,
+  api_server: String,
+  html_host: String,
+  raw_host: String,
+  raw_path_length: usize
 }
 
 impl Gist {
   pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Gist {
+    let server = super::server(&config.gist.server, DEFAULT_SERVER);
+    let api_server = super::server(&config.gist.api_server, DEFAULT_API_SERVER);
+    let raw_server = super::server(&config.gist.raw_server, DEFAULT_RAW_SERVER);
+    // github enterprise serves raw gists under a path, like https://github.example.com/gist/raw
+    let raw_path_length = Url::parse(&raw_server).ok()
+      .and_then(|u| u.path_segments().map(|s| s.filter(|x| !x.is_empty()).count()))
+      .unwrap_or(0);
     Gist {
+      html_host: super::host(&server),
+      raw_host: super::host(&raw_server),
       config: config,
       cli: cli,
-      client: ::new_client()
+      client: ::transport::new_client(),
+      server: server,
+      api_server: api_server,
+      raw_path_length: raw_path_length
     }
   }
 
   fn add_headers<'a>(&self, rb: RequestBuilder<'a>) -> RequestBuilder<'a> {
     let mut headers = Headers::new();
     headers.set(ContentType::json());
-    headers.set(UserAgent(format!("bins/{}", crate_version!())));
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
     if let Some(true) = self.cli.authed.or(self.config.defaults.authed) {
-      if let Some(ref username) = self.config.gist.username {
-        if let Some(ref access_token) = self.config.gist.access_token {
-          if !username.is_empty() && !access_token.is_empty() {
-            headers.set(Authorization(Basic {
-              username: username.to_owned(),
-              password: Some(access_token.to_owned())
-            }));
-          }
-        }
+      if let Some(authorization) = self.authorization() {
+        headers.set(authorization);
       }
     }
     rb.headers(headers)
   }
 
+  fn authorization(&self) -> Option<Authorization<Basic>> {
+    let config_values = (&self.config.gist.username, &self.config.gist.access_token);
+    match config_values {
+      (&Some(ref u), &Some(ref at)) if !u.is_empty() && !at.is_empty() => Some(Authorization(Basic {
+        username: u.to_owned(),
+        password: Some(at.to_owned())
+      })),
+      _ => None
+    }
+  }
+
   fn get_gist(&self, id: &str) -> Result<RemoteGistPaste> {
     debug!("getting gist for ID {}", id);
-    let builder = self.client.get(&format!("https://api.github.com/gists/{}", id));
+    let builder = self.client.get(&format!("{}/gists/{}", self.api_server, id));
     let mut res = self.add_headers(builder).send()?;
     let mut content = String::new();
     res.read_to_string(&mut content)?;
@@ -70,11 +94,11 @@ impl Bin for Gist {
   }
 
   fn html_host(&self) -> &str {
-    "gist.github.com"
+    &self.html_host
   }
 
   fn raw_host(&self) -> &str {
-    "gist.githubusercontent.com"
+    &self.raw_host
   }
 }
 
@@ -102,7 +126,8 @@ impl CreatesHtmlUrls for Gist {
     let urls: Vec<PasteUrl> = gist.files.iter()
       .map(|(name, _)| PasteUrl::html(
         Some(PasteFileName::Explicit(name.clone())),
-        format!("https://gist.github.com/{}/#file-{}",
+        format!("{}/{}/#file-{}",
+                self.server,
                 id,
                 name.chars()
                   .map(|c| c.to_lowercase().collect::<String>())
@@ -139,7 +164,7 @@ impl CreatesRawUrls for Gist {
     url.set_fragment(None);
     url.set_query(None);
     let segments: Vec<&str> = option!(url.path_segments()).collect();
-    segments.get(1).map(|x| x.to_string())
+    segments.get(self.raw_path_length + 1).map(|x| x.to_string())
   }
 }
 
@@ -150,7 +175,10 @@ impl HasFeatures for Gist {
          BinFeature::Authed,
          BinFeature::Anonymous,
          BinFeature::MultiFile,
-         BinFeature::SingleNaming]
+         BinFeature::SingleNaming,
+         BinFeature::Deletable,
+         BinFeature::Updatable,
+         BinFeature::Described]
   }
 }
 
@@ -158,14 +186,15 @@ impl Uploads for Gist {
   fn upload(&self, contents: &[UploadFile], _: bool) -> Result<Vec<PasteUrl>> {
     let mut files = BTreeMap::new();
     for file in contents {
-      files.insert(file.name.clone(), UploadGistFile { content: file.content.clone() });
+      files.insert(file.name.clone(), UploadGistFile { content: file.text()?.to_owned() });
     }
     let upload_file = UploadGistPaste {
+      description: self.cli.description.clone(),
       public: self.cli.private.or(self.config.defaults.private).map(|x| !x).unwrap_or(false),
       files: files
     };
     let upload_json = serde_json::to_string(&upload_file)?;
-    let builder = self.client.post("https://api.github.com/gists").body(&upload_json);
+    let builder = self.client.post(&format!("{}/gists", self.api_server)).body(&upload_json);
     let mut res = self.add_headers(builder).send()?;
     let mut content = String::new();
     res.read_to_string(&mut content)?;
@@ -180,10 +209,57 @@ impl Uploads for Gist {
   }
 }
 
+impl Updates for Gist {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let mut files = BTreeMap::new();
+    for file in contents {
+      files.insert(file.name.clone(), UploadGistFile { content: file.text()?.to_owned() });
+    }
+    let update_file = UpdateGistPaste {
+      description: self.cli.description.clone(),
+      files: files
+    };
+    let update_json = serde_json::to_string(&update_file)?;
+    let builder = self.client.patch(&format!("{}/gists/{}", self.api_server, id)).body(&update_json);
+    let mut res = self.add_headers(builder).send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status.class().default_code() != ::hyper::Ok {
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    let paste: RemoteGistPaste = serde_json::from_str(&content)?;
+    match paste.html_url {
+      Some(u) => Ok(vec![PasteUrl::html(None, u)]),
+      None => Err(ErrorKind::InvalidResponse.into())
+    }
+  }
+}
+
+impl Deletes for Gist {
+  fn delete(&self, id: &str) -> Result<()> {
+    let authorization = match self.authorization() {
+      Some(a) => a,
+      None => bail!("a gist username and access token must be set to delete gists")
+    };
+    let builder = self.client.delete(&format!("{}/gists/{}", self.api_server, id));
+    let mut res = self.add_headers(builder).header(authorization).send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status != ::hyper::status::StatusCode::NoContent {
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    Ok(())
+  }
+}
+
 impl HasClient for Gist {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
 
 #[derive(Debug, Deserialize)]
@@ -204,10 +280,19 @@ struct RemoteGistFile {
 
 #[derive(Debug, Serialize)]
 struct UploadGistPaste {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  description: Option<String>,
   public: bool,
   files: BTreeMap<String, UploadGistFile>
 }
 
+#[derive(Debug, Serialize)]
+struct UpdateGistPaste {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  description: Option<String>,
+  files: BTreeMap<String, UploadGistFile>
+}
+
 #[derive(Debug, Serialize)]
 struct UploadGistFile {
   content: String
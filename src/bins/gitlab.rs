@@ -0,0 +1,333 @@
+use url::Url;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use hyper::header::{Headers, ContentType, UserAgent};
+use serde_json;
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, RequestBuilder, Request, Response, Transport};
+use config::{Config, CommandLineOptions};
+
+use std::io::Read;
+use std::sync::Arc;
+
+const DEFAULT_URL: &'static str = "https://gitlab.com";
+
+pub struct GitLab {
+  config: Arc<Config>,
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  base_url: String,
+  host: String
+}
+
+impl GitLab {
+  pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> GitLab {
+    let base_url = config.gitlab.url.clone()
+      .and_then(|x| if x.trim().is_empty() { None } else { Some(x) })
+      .unwrap_or_else(|| DEFAULT_URL.to_owned())
+      .trim_right_matches('/')
+      .to_owned();
+    let host = super::host(&base_url);
+    let mut gitlab = GitLab {
+      config: config,
+      cli: cli,
+      client: ::transport::new_client(),
+      base_url: base_url,
+      host: host
+    };
+    let client = gitlab.client.clone();
+    gitlab.set_client(client);
+    gitlab
+  }
+
+  fn token(&self) -> Option<&str> {
+    match self.config.gitlab.token {
+      Some(ref t) if !t.is_empty() => Some(t),
+      _ => None
+    }
+  }
+
+  fn add_headers<'a>(&self, rb: RequestBuilder<'a>) -> Result<RequestBuilder<'a>> {
+    let token = match self.token() {
+      Some(t) => t,
+      None => bail!("a gitlab access token must be set to use gitlab snippets")
+    };
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
+    headers.set_raw("PRIVATE-TOKEN", vec![token.as_bytes().to_vec()]);
+    Ok(rb.headers(headers))
+  }
+
+  fn send(&self, rb: RequestBuilder) -> Result<Vec<u8>> {
+    let mut res = self.add_headers(rb)?.send()?;
+    let mut content = Vec::new();
+    res.read_to_end(&mut content)?;
+    if res.status.class().default_code() != ::hyper::Ok {
+      debug!("bad status code");
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(String::from_utf8_lossy(&content).into_owned())).into());
+    }
+    Ok(content)
+  }
+
+  fn api_url(&self, path: &str) -> String {
+    format!("{}/api/v4/{}", self.base_url, path)
+  }
+
+  fn get_snippet(&self, id: &str) -> Result<RemoteSnippet> {
+    debug!("getting snippet for ID {}", id);
+    let content = self.send(self.client.get(&self.api_url(&format!("snippets/{}", id))))?;
+    serde_json::from_slice(&content).chain_err(|| "could not parse gitlab response")
+  }
+
+  fn visibility(&self) -> &str {
+    if !self.cli.private.or(self.config.defaults.private).unwrap_or(false) {
+      return "public";
+    }
+    match self.config.gitlab.private_visibility {
+      Some(ref v) if v == "internal" => "internal",
+      _ => "private"
+    }
+  }
+
+  fn id_from_url(&self, url: &str) -> Option<String> {
+    let url = option!(Url::parse(url).ok());
+    let mut segments = option!(url.path_segments());
+    option!(segments.position(|x| x == "snippets"));
+    segments.next().map(|x| x.to_owned())
+  }
+}
+
+impl Bin for GitLab {
+  fn name(&self) -> &str {
+    "gitlab"
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for GitLab {}
+
+impl CreatesUrls for GitLab {}
+
+impl FormatsUrls for GitLab {}
+
+impl FormatsHtmlUrls for GitLab {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    Some(format!("{}/-/snippets/{}", self.base_url, id))
+  }
+}
+
+impl FormatsRawUrls for GitLab {
+  fn format_raw_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl CreatesHtmlUrls for GitLab {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let snippet = self.get_snippet(id)?;
+    let urls: Vec<PasteUrl> = snippet.files.iter()
+      .map(|file| PasteUrl::html(
+        Some(PasteFileName::Explicit(file.path.clone())),
+        snippet.web_url.clone()
+      ))
+      .collect();
+    Ok(urls)
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl CreatesRawUrls for GitLab {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let snippet = self.get_snippet(id)?;
+    // raw urls on the web interface need a session for private snippets, so use the api, which the client sends the
+    // token to
+    let urls = snippet.files.into_iter()
+      .map(|file| {
+        let git_ref = Url::parse(&file.raw_url).ok()
+          .and_then(|u| u.path_segments()
+            .and_then(|s| s.skip_while(|x| *x != "raw").nth(1).map(|x| x.to_owned())))
+          .unwrap_or_else(|| String::from("main"));
+        let api_url = self.api_url(&format!("snippets/{}/files/{}/{}/raw",
+          id,
+          utf8_percent_encode(&git_ref, PATH_SEGMENT_ENCODE_SET),
+          utf8_percent_encode(&file.path, PATH_SEGMENT_ENCODE_SET)));
+        PasteUrl::raw(Some(PasteFileName::Explicit(file.path)), api_url)
+      })
+      .collect();
+    Ok(urls)
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl HasFeatures for GitLab {
+  fn features(&self) -> Vec<BinFeature> {
+    vec![BinFeature::Public,
+         BinFeature::Private,
+         BinFeature::Authed,
+         BinFeature::MultiFile,
+         BinFeature::SingleNaming,
+         BinFeature::Deletable,
+         BinFeature::Updatable,
+         BinFeature::Titled,
+         BinFeature::Described]
+  }
+}
+
+impl Uploads for GitLab {
+  fn upload(&self, contents: &[UploadFile], _: bool) -> Result<Vec<PasteUrl>> {
+    if let Some(false) = self.cli.authed.or(self.config.defaults.authed) {
+      bail!("gitlab does not support anonymous snippets");
+    }
+    let mut files = Vec::with_capacity(contents.len());
+    for file in contents {
+      files.push(UploadSnippetFile {
+        action: None,
+        file_path: file.name.clone(),
+        content: file.text()?.to_owned()
+      });
+    }
+    let upload = UploadSnippet {
+      // gitlab requires a title, so use the first file's name if there is none
+      title: self.cli.title.clone().unwrap_or_else(|| contents.get(0).map(|f| f.name.clone()).unwrap_or_else(|| String::from("bins"))),
+      description: self.cli.description.clone(),
+      visibility: self.visibility().to_owned(),
+      files: files
+    };
+    let upload_json = serde_json::to_string(&upload)?;
+    let content = self.send(self.client.post(&self.api_url("snippets")).body(&upload_json))?;
+    let snippet: RemoteSnippet = serde_json::from_slice(&content).chain_err(|| "could not parse gitlab response")?;
+    Ok(vec![PasteUrl::html(None, snippet.web_url)])
+  }
+}
+
+impl Updates for GitLab {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let snippet = self.get_snippet(id)?;
+    let mut files = Vec::with_capacity(contents.len());
+    for file in contents {
+      let action = if snippet.files.iter().any(|f| f.path == file.name) { "update" } else { "create" };
+      files.push(UploadSnippetFile {
+        action: Some(action),
+        file_path: file.name.clone(),
+        content: file.text()?.to_owned()
+      });
+    }
+    let update = UpdateSnippet {
+      title: self.cli.title.clone(),
+      description: self.cli.description.clone(),
+      files: files
+    };
+    let update_json = serde_json::to_string(&update)?;
+    let content = self.send(self.client.put(&self.api_url(&format!("snippets/{}", id))).body(&update_json))?;
+    let snippet: RemoteSnippet = serde_json::from_slice(&content).chain_err(|| "could not parse gitlab response")?;
+    Ok(vec![PasteUrl::html(None, snippet.web_url)])
+  }
+}
+
+impl Deletes for GitLab {
+  fn delete(&self, id: &str) -> Result<()> {
+    let builder = self.client.delete(&self.api_url(&format!("snippets/{}", id)));
+    let mut res = self.add_headers(builder)?.send()?;
+    let mut content = Vec::new();
+    res.read_to_end(&mut content)?;
+    if res.status != ::hyper::status::StatusCode::NoContent {
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(String::from_utf8_lossy(&content).into_owned())).into());
+    }
+    Ok(())
+  }
+}
+
+impl HasClient for GitLab {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    let client = token_client(client, &self.base_url, self.token());
+    self.client = client;
+  }
+}
+
+/// Wraps `inner` in a `TokenTransport` for the instance at `base_url`.
+fn token_client(inner: HttpClient, base_url: &str, token: Option<&str>) -> HttpClient {
+  HttpClient::new(TokenTransport {
+    inner: inner,
+    base_url: format!("{}/", base_url),
+    token: token.map(|x| x.to_owned())
+  })
+}
+
+/// Sends the access token with requests to the GitLab instance that do not already have it, so the raw files of private
+/// snippets can be downloaded like any other raw URL.
+struct TokenTransport {
+  inner: HttpClient,
+  base_url: String,
+  token: Option<String>
+}
+
+impl Transport for TokenTransport {
+  fn send(&self, mut request: Request) -> Result<Response> {
+    if let Some(ref token) = self.token {
+      if request.url.as_str().starts_with(&self.base_url) && request.headers.get_raw("PRIVATE-TOKEN").is_none() {
+        request.headers.set_raw("PRIVATE-TOKEN", vec![token.as_bytes().to_vec()]);
+      }
+    }
+    self.inner.send(request)
+  }
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteSnippet {
+  web_url: String,
+  #[serde(default)]
+  files: Vec<RemoteSnippetFile>
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoteSnippetFile {
+  path: String,
+  raw_url: String
+}
+
+#[derive(Debug, Serialize)]
+struct UploadSnippet {
+  title: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  description: Option<String>,
+  visibility: String,
+  files: Vec<UploadSnippetFile>
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateSnippet {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  title: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  description: Option<String>,
+  files: Vec<UploadSnippetFile>
+}
+
+#[derive(Debug, Serialize)]
+struct UploadSnippetFile {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  action: Option<&'static str>,
+  file_path: String,
+  content: String
+}
@@ -1,22 +1,39 @@
 use url::Url;
-use hyper::Client;
 use serde_json;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::{Config, ConfigHastebin};
 
 use std::io::Read;
+use std::sync::Arc;
+
+const DEFAULT_SERVER: &'static str = "https://hastebin.com";
 
 pub struct Hastebin {
-  client: Client
+  name: String,
+  client: HttpClient,
+  server: String,
+  host: String
 }
 
 impl Hastebin {
-  pub fn new() -> Hastebin {
+  pub fn new(config: Arc<Config>) -> Hastebin {
+    Hastebin::named("hastebin", &config.hastebin)
+  }
+
+  /// Creates a hastebin bin with its own name and server, so more than one hastebin server can be used at once.
+  pub fn named(name: &str, settings: &ConfigHastebin) -> Hastebin {
+    let server = super::server(&settings.server, DEFAULT_SERVER);
+    let host = super::host(&server);
     Hastebin {
-      client: ::new_client()
+      name: name.to_owned(),
+      client: ::transport::new_client(),
+      server: server,
+      host: host
     }
   }
 
@@ -30,15 +47,15 @@ impl Hastebin {
 
 impl Bin for Hastebin {
   fn name(&self) -> &str {
-    "hastebin"
+    &self.name
   }
 
   fn html_host(&self) -> &str {
-    "hastebin.com"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "hastebin.com"
+    &self.host
   }
 }
 
@@ -50,13 +67,13 @@ impl FormatsUrls for Hastebin {}
 
 impl FormatsHtmlUrls for Hastebin {
   fn format_html_url(&self, id: &str) -> Option<String> {
-    Some(format!("https://hastebin.com/{}", id))
+    Some(format!("{}/{}", self.server, id))
   }
 }
 
 impl FormatsRawUrls for Hastebin {
   fn format_raw_url(&self, id: &str) -> Option<String> {
-    Some(format!("https://hastebin.com/raw/{}", id))
+    Some(format!("{}/raw/{}", self.server, id))
   }
 }
 
@@ -64,29 +81,7 @@ impl CreatesHtmlUrls for Hastebin {
   fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     let html_url = self.format_html_url(id).unwrap();
     let raw_url = self.format_raw_url(id).unwrap();
-    let mut res = self.client.get(&raw_url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self.format_html_url(&id).unwrap())).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(html_url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &raw_url, &html_url, |id| self.format_html_url(id).unwrap())
   }
 
   fn id_from_html_url(&self, url: &str) -> Option<String> {
@@ -98,29 +93,7 @@ impl CreatesRawUrls for Hastebin {
   fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     debug!("creating raw url for {}", id);
     let raw_url = self.format_raw_url(id).unwrap();
-    let mut res = self.client.get(&raw_url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self.format_raw_url(&id).unwrap())).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(raw_url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &raw_url, &raw_url, |id| self.format_raw_url(id).unwrap())
   }
 
   fn id_from_raw_url(&self, url: &str) -> Option<String> {
@@ -137,8 +110,8 @@ impl HasFeatures for Hastebin {
 impl UploadsSingleFiles for Hastebin {
   fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
     debug!("uploading single file");
-    let mut res = self.client.post("https://hastebin.com/documents")
-      .body(&file.content)
+    let mut res = self.client.post(&format!("{}/documents", self.server))
+      .body(file.text()?)
       .send()?;
     debug!("res: {:?}", res);
     let mut content = String::new();
@@ -161,10 +134,18 @@ impl UploadsSingleFiles for Hastebin {
   }
 }
 
+impl Updates for Hastebin {}
+
+impl Deletes for Hastebin {}
+
 impl HasClient for Hastebin {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
 
 #[derive(Debug, Deserialize)]
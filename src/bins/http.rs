@@ -0,0 +1,292 @@
+use url::Url;
+use hyper::header::{Headers, ContentType, UserAgent};
+use hyper::method::Method;
+use regex::{self, Regex};
+use serde_json::{self, Value};
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::{ConfigBin, CommandLineOptions};
+use super::multipart::FormData;
+
+use std::io::Read;
+use std::sync::Arc;
+
+/// A bin defined in the configuration file instead of in bins itself.
+pub struct Http {
+  name: String,
+  definition: ConfigBin,
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  method: Method,
+  url_regex: Option<Regex>,
+  html_id: Regex,
+  raw_id: Regex,
+  html_host: String,
+  raw_host: String
+}
+
+impl Http {
+  pub fn new(name: &str, definition: &ConfigBin, cli: Arc<CommandLineOptions>) -> Result<Http> {
+    if definition.upload_url.is_none() {
+      bail!("{} does not have an upload_url", name);
+    }
+    let html_url = match definition.html_url {
+      Some(ref u) => u.clone(),
+      None => bail!("{} does not have an html_url", name)
+    };
+    let raw_url = definition.raw_url.clone().unwrap_or_else(|| html_url.clone());
+    let method = definition.method.as_ref().map(|x| x.to_uppercase()).unwrap_or_else(|| String::from("POST"));
+    let method = method.parse().chain_err(|| format!("{} has an invalid method", name))?;
+    let url_regex = match definition.url_regex {
+      Some(ref r) => Some(Regex::new(r).chain_err(|| format!("{} has an invalid url_regex", name))?),
+      None => None
+    };
+    Ok(Http {
+      name: name.to_owned(),
+      definition: definition.clone(),
+      cli: cli,
+      client: ::transport::new_client(),
+      method: method,
+      url_regex: url_regex,
+      html_id: id_regex(&html_url).chain_err(|| format!("{} has an invalid html_url", name))?,
+      raw_id: id_regex(&raw_url).chain_err(|| format!("{} has an invalid raw_url", name))?,
+      html_host: host(&html_url).chain_err(|| format!("{} has an invalid html_url", name))?,
+      raw_host: host(&raw_url).chain_err(|| format!("{} has an invalid raw_url", name))?
+    })
+  }
+
+  fn raw_url_template(&self) -> &str {
+    self.definition.raw_url.as_ref().or(self.definition.html_url.as_ref()).map(|x| x.as_str()).unwrap_or_default()
+  }
+
+  /// Fills in the placeholders in a template, using `encode` on each value. The template is read once, so placeholders
+  /// inside the values are left alone.
+  fn fill<F>(&self, template: &str, file: &UploadFile, encode: F) -> Result<String>
+    where F: Fn(&str) -> String
+  {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+      filled.push_str(&rest[..start]);
+      rest = &rest[start..];
+      let placeholder = rest.find('}').map(|end| &rest[1..end]);
+      let value = match placeholder {
+        Some("name") => Some(file.name.as_str()),
+        Some("language") => Some(file.language.as_ref().map(|x| x.as_str()).unwrap_or_default()),
+        Some("title") => Some(self.cli.title.as_ref().map(|x| x.as_str()).unwrap_or_default()),
+        Some("description") => Some(self.cli.description.as_ref().map(|x| x.as_str()).unwrap_or_default()),
+        Some("content") => Some(file.text()?),
+        _ => None
+      };
+      match (placeholder, value) {
+        (Some(p), Some(v)) => {
+          filled.push_str(&encode(v));
+          rest = &rest[p.len() + 2..];
+        },
+        // anything else in braces is kept as it is
+        _ => {
+          filled.push('{');
+          rest = &rest[1..];
+        }
+      }
+    }
+    filled.push_str(rest);
+    Ok(filled)
+  }
+
+  fn body(&self, file: &UploadFile, headers: &mut Headers) -> Result<Vec<u8>> {
+    let kind = self.definition.body.as_ref().map(|x| x.as_str()).unwrap_or("raw");
+    match kind {
+      "raw" => Ok(file.content.clone()),
+      "json" => {
+        let template = match self.definition.template {
+          Some(ref t) => t,
+          None => bail!("{} uses a json body but does not have a template", self.name)
+        };
+        // values are inserted as json strings, so templates should not quote placeholders
+        let body = self.fill(template, file, |v| serde_json::to_string(v).unwrap_or_default())?;
+        headers.set(ContentType::json());
+        Ok(body.into_bytes())
+      },
+      "form" => {
+        let mut form = FormData::new();
+        if let Some(ref field) = self.definition.file_field {
+          form.file(field, &file.name, &file.content);
+        }
+        for (name, template) in &self.definition.fields {
+          form.text(name, &self.fill(template, file, |v| v.to_owned())?);
+        }
+        headers.set(form.content_type());
+        Ok(form.finish())
+      },
+      k => bail!("{} has an unknown body type \"{}\" (expected raw, json or form)", self.name, k)
+    }
+  }
+
+  /// Finds the URL or ID of the paste in the response, using `url_path` or `url_regex` if they are set.
+  fn extract(&self, response: &str) -> Result<String> {
+    if let Some(ref path) = self.definition.url_path {
+      let json: Value = serde_json::from_str(response).chain_err(|| ErrorKind::InvalidResponse)?;
+      return match json.pointer(&json_pointer(path)) {
+        Some(&Value::String(ref s)) => Ok(s.clone()),
+        Some(v) if v.is_number() => Ok(v.to_string()),
+        _ => bail!("{} responded without anything at {}", self.name, path)
+      };
+    }
+    if let Some(ref regex) = self.url_regex {
+      let captures = match regex.captures(response) {
+        Some(c) => c,
+        None => bail!("{} responded with something that did not match its url_regex", self.name)
+      };
+      let found = captures.get(1).or_else(|| captures.get(0)).map(|m| m.as_str().to_owned());
+      return found.ok_or_else(|| ErrorKind::InvalidResponse.into());
+    }
+    Ok(response.trim().to_owned())
+  }
+
+  fn id_from_url(&self, regex: &Regex, url: &str) -> Option<String> {
+    regex.captures(url).and_then(|c| c.get(1)).map(|m| m.as_str().to_owned())
+  }
+}
+
+impl Bin for Http {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn html_host(&self) -> &str {
+    &self.html_host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.raw_host
+  }
+}
+
+impl ManagesUrls for Http {}
+
+impl CreatesUrls for Http {}
+
+impl FormatsUrls for Http {}
+
+impl FormatsHtmlUrls for Http {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    self.definition.html_url.as_ref().map(|u| u.replace("{id}", id))
+  }
+}
+
+impl FormatsRawUrls for Http {
+  fn format_raw_url(&self, id: &str) -> Option<String> {
+    Some(self.raw_url_template().replace("{id}", id))
+  }
+}
+
+impl CreatesHtmlUrls for Http {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    Ok(vec![PasteUrl::html(None, self.format_html_url(id).unwrap())])
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(&self.html_id, url)
+  }
+}
+
+impl CreatesRawUrls for Http {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    debug!("creating raw url for {}", id);
+    let raw_url = self.format_raw_url(id).unwrap();
+    super::download_or_index(self, id, &raw_url, &raw_url, |id| self.format_raw_url(id).unwrap())
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(&self.raw_id, url)
+  }
+}
+
+impl HasFeatures for Http {
+  fn features(&self) -> Vec<BinFeature> {
+    let mut features = vec![BinFeature::Public, BinFeature::Anonymous];
+    if let Some(true) = self.definition.binary {
+      features.push(BinFeature::BinaryFiles);
+    }
+    features
+  }
+}
+
+impl UploadsSingleFiles for Http {
+  fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
+    debug!("uploading single file to {}", self.name);
+    let mut headers = Headers::new();
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
+    let body = self.body(file, &mut headers)?;
+    for (name, value) in &self.definition.headers {
+      headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+    }
+    let upload_url = self.fill(self.definition.upload_url.as_ref().unwrap(), file, |v| {
+      ::url::percent_encoding::utf8_percent_encode(v, ::url::percent_encoding::PATH_SEGMENT_ENCODE_SET).to_string()
+    })?;
+    let mut res = self.client.request(self.method.clone(), &upload_url)
+      .headers(headers)
+      .body(&body[..])
+      .send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status.class().default_code() != ::hyper::Ok {
+      debug!("bad status code");
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    let found = self.extract(&content)?;
+    // the response may have the whole url or just the id
+    let url = if Url::parse(&found).is_ok() {
+      found
+    } else {
+      self.format_html_url(&found).unwrap()
+    };
+    Ok(PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), url))
+  }
+}
+
+impl Updates for Http {}
+
+impl Deletes for Http {}
+
+impl HasClient for Http {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
+
+/// Turns a URL template into a regex that captures the `{id}` placeholder.
+fn id_regex(template: &str) -> Result<Regex> {
+  let parts: Vec<String> = template.split("{id}").map(regex::escape).collect();
+  if parts.len() != 2 {
+    bail!("url templates must contain {{id}} exactly once");
+  }
+  Regex::new(&format!("^{}([^/?#]+){}", parts[0], parts[1])).chain_err(|| "could not create a pattern for the url template")
+}
+
+fn host(template: &str) -> Result<String> {
+  let url = Url::parse(&template.replace("{id}", "id"))?;
+  super::url_host(&url).ok_or_else(|| ErrorKind::Msg("url was missing a host".into()).into())
+}
+
+/// Converts a simple JSONPath, like `$.data.url` or `$.files[0].url`, into a JSON pointer. JSON pointers are returned
+/// unchanged.
+fn json_pointer(path: &str) -> String {
+  if path.starts_with('/') {
+    return path.to_owned();
+  }
+  path.trim_left_matches('$')
+    .split(|c| c == '.' || c == '[')
+    .map(|x| x.trim_right_matches(']').trim_matches(|c| c == '\'' || c == '"'))
+    .filter(|x| !x.is_empty())
+    .fold(String::new(), |pointer, part| pointer + "/" + part)
+}
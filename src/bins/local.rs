@@ -0,0 +1,359 @@
+use url::Url;
+use url::percent_encoding::{utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+use rand::{Rng, thread_rng};
+use serde_json;
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, Replay};
+use config::Config;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A bin that "uploads" by writing files into a directory, which may be a shared network mount.
+///
+/// Each paste is a directory named after its ID, containing its files. Pastes with more than one file are uploaded as
+/// one paste per file and an `index.json` paste listing them, like any other single-file bin.
+pub struct Local {
+  client: HttpClient,
+  directory: Option<PathBuf>,
+  template: String,
+  host: String
+}
+
+impl Local {
+  pub fn new(config: Arc<Config>) -> Local {
+    let directory = config.local.directory.as_ref()
+      .map(|x| x.trim())
+      .and_then(|x| if x.is_empty() { None } else { Some(x) })
+      .map(|x| {
+        let path = PathBuf::from(x);
+        // file urls need an absolute path
+        if path.is_absolute() {
+          path
+        } else {
+          env::current_dir().map(|d| d.join(&path)).unwrap_or(path)
+        }
+      });
+    let template = match config.local.url.as_ref().map(|x| x.trim()).and_then(|x| if x.is_empty() { None } else { Some(x) }) {
+      Some(u) if u.contains("{id}") => u.to_owned(),
+      Some(u) => format!("{}/{{id}}/{{name}}", u.trim_right_matches('/')),
+      None => {
+        let base = directory.as_ref()
+          .and_then(|d| Url::from_directory_path(d).ok())
+          .map(|u| u.to_string())
+          .unwrap_or_else(|| "file:///".to_owned());
+        format!("{}{{id}}/{{name}}", base)
+      }
+    };
+    // file urls have no host, so they are matched to bins with an empty host
+    let host = super::host(&template);
+    Local {
+      // pastes are read from disk, so the client is never used
+      client: HttpClient::new(Replay::new(Vec::new())),
+      directory: directory,
+      template: template,
+      host: host
+    }
+  }
+
+  fn directory(&self) -> Result<&Path> {
+    match self.directory {
+      Some(ref d) => Ok(d),
+      None => bail!("local needs a directory to be set in the config file")
+    }
+  }
+
+  /// The directory for the paste with the given ID. IDs are checked, so they cannot point outside of the directory.
+  fn paste_directory(&self, id: &str) -> Result<PathBuf> {
+    if id.is_empty() || !id.chars().all(is_id_char) {
+      bail!("{} is not a valid local paste ID", id);
+    }
+    Ok(self.directory()?.join(id))
+  }
+
+  fn url(&self, id: &str, name: &str) -> String {
+    self.template
+      .replace("{id}", id)
+      .replace("{name}", &utf8_percent_encode(name, PATH_SEGMENT_ENCODE_SET).to_string())
+  }
+
+  fn id_from_url(&self, url: &str) -> Option<String> {
+    let prefix = option!(self.template.split("{id}").next());
+    if !url.starts_with(prefix) {
+      return None;
+    }
+    let id: String = url[prefix.len()..].chars().take_while(|&c| is_id_char(c)).collect();
+    if id.is_empty() {
+      None
+    } else {
+      Some(id)
+    }
+  }
+
+  fn file_name(&self, name: &str) -> Result<String> {
+    let file_name = Path::new(name).file_name().and_then(|x| x.to_str());
+    match file_name {
+      Some(n) => Ok(n.to_owned()),
+      None => bail!("{} is not a valid file name", name)
+    }
+  }
+
+  /// The names and contents of the files in a paste, sorted by name.
+  fn read_files(&self, id: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    let dir = self.paste_directory(id)?;
+    if !dir.is_dir() {
+      bail!("local has no paste {}", id);
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir).chain_err(|| format!("could not read {}", dir.to_string_lossy()))? {
+      let entry = entry?;
+      if !entry.file_type()?.is_file() {
+        continue;
+      }
+      let name = entry.file_name().to_string_lossy().into_owned();
+      let mut content = Vec::new();
+      File::open(entry.path())?.read_to_end(&mut content)?;
+      files.push((name, content));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+  }
+
+  /// Writes a file into a paste's directory, returning the name it was written with.
+  fn write_file(&self, dir: &Path, file: &UploadFile) -> Result<String> {
+    let name = self.file_name(&file.name)?;
+    let path = dir.join(&name);
+    File::create(&path)
+      .and_then(|mut f| f.write_all(&file.content))
+      .chain_err(|| format!("could not write {}", path.to_string_lossy()))?;
+    Ok(name)
+  }
+
+  /// The files listed in a paste, with the ID of the paste each is in, if the paste is an index made by `Uploads`.
+  fn index_entries(&self, files: &[(String, Vec<u8>)]) -> Result<Option<Vec<(IndexedFile, String)>>> {
+    if files.len() != 1 || files[0].0 != "index.json" {
+      return Ok(None);
+    }
+    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_slice(&files[0].1);
+    let is = match parsed {
+      Ok(is) => is,
+      Err(_) => return Ok(None)
+    };
+    let mut entries = Vec::with_capacity(is.len());
+    for indexed in is {
+      let indexed_id = match self.id_from_url(&indexed.url) {
+        Some(i) => i,
+        None => bail!("one of the URLs in the index did not contain a valid ID")
+      };
+      entries.push((indexed, indexed_id));
+    }
+    Ok(Some(entries))
+  }
+
+  fn write_index(&self, id: &str, entries: &[(IndexedFile, String)]) -> Result<()> {
+    let index: Vec<&IndexedFile> = entries.iter().map(|&(ref i, _)| i).collect();
+    let content = serde_json::to_vec(&index)?;
+    self.write_file(&self.paste_directory(id)?, &UploadFile::new("index.json".to_owned(), content))?;
+    Ok(())
+  }
+
+  /// The files in a paste, with the files listed in an index read in its place.
+  fn downloaded_files(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let files = self.read_files(id)?;
+    if let Some(entries) = self.index_entries(&files)? {
+      debug!("file was an index, so reading its files");
+      let mut urls = Vec::new();
+      for (indexed, indexed_id) in entries {
+        for (name, content) in self.read_files(&indexed_id)? {
+          let url = self.url(&indexed_id, &name);
+          urls.push(PasteUrl::Downloaded(url, DownloadedFile::new(PasteFileName::Explicit(indexed.name.clone()), content)));
+        }
+      }
+      return Ok(urls);
+    }
+    Ok(files.into_iter()
+      .map(|(name, content)| {
+        let url = self.url(id, &name);
+        PasteUrl::Downloaded(url, DownloadedFile::new(PasteFileName::Explicit(name), content))
+      })
+      .collect())
+  }
+}
+
+/// Paste IDs are ASCII letters and digits, so they are safe to use as directory names.
+fn is_id_char(c: char) -> bool {
+  match c {
+    'a'...'z' | 'A'...'Z' | '0'...'9' => true,
+    _ => false
+  }
+}
+
+impl Bin for Local {
+  fn name(&self) -> &str {
+    "local"
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for Local {}
+
+impl CreatesUrls for Local {}
+
+impl FormatsUrls for Local {}
+
+impl FormatsHtmlUrls for Local {
+  fn format_html_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl FormatsRawUrls for Local {
+  fn format_raw_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl CreatesHtmlUrls for Local {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    Ok(self.read_files(id)?.into_iter()
+      .map(|(name, _)| {
+        let url = self.url(id, &name);
+        PasteUrl::html(Some(PasteFileName::Explicit(name)), url)
+      })
+      .collect())
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl CreatesRawUrls for Local {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    debug!("reading local paste {}", id);
+    self.downloaded_files(id)
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url)
+  }
+}
+
+impl HasFeatures for Local {
+  fn features(&self) -> Vec<BinFeature> {
+    vec![BinFeature::Public, BinFeature::Anonymous, BinFeature::BinaryFiles, BinFeature::Updatable, BinFeature::Deletable]
+  }
+}
+
+impl UploadsSingleFiles for Local {
+  fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
+    let directory = self.directory()?;
+    fs::create_dir_all(directory).chain_err(|| format!("could not create {}", directory.to_string_lossy()))?;
+    let (id, dir) = loop {
+      let id: String = thread_rng().gen_ascii_chars().take(10).collect();
+      let dir = directory.join(&id);
+      // create_dir fails if the directory exists, so two uploads never share an ID
+      match fs::create_dir(&dir) {
+        Ok(_) => break (id, dir),
+        Err(ref e) if e.kind() == ::std::io::ErrorKind::AlreadyExists => continue,
+        Err(e) => return Err(e).chain_err(|| format!("could not create {}", dir.to_string_lossy()))
+      }
+    };
+    debug!("writing local paste {}", id);
+    let name = match self.write_file(&dir, file) {
+      Ok(n) => n,
+      Err(e) => {
+        // an empty directory would be a paste with no files
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e);
+      }
+    };
+    Ok(PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), self.url(&id, &name)))
+  }
+}
+
+impl Updates for Local {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let dir = self.paste_directory(id)?;
+    if !dir.is_dir() {
+      bail!("local has no paste {}", id);
+    }
+    // files listed in an index are updated in their own pastes, and new files are added to the index
+    if let Some(mut entries) = self.index_entries(&self.read_files(id)?)? {
+      let mut urls = Vec::with_capacity(contents.len());
+      for file in contents {
+        let existing = entries.iter().find(|&&(ref i, _)| i.name == file.name).map(|&(_, ref i)| i.clone());
+        let url = match existing {
+          Some(entry_id) => {
+            let name = self.write_file(&self.paste_directory(&entry_id)?, file)?;
+            PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), self.url(&entry_id, &name))
+          },
+          None => {
+            let url = self.upload_single(file)?;
+            let entry_id = match self.id_from_url(url.url()) {
+              Some(i) => i,
+              None => bail!("could not parse the ID of the new paste for {}", file.name)
+            };
+            entries.push((IndexedFile::new(file.name.clone(), url.url().to_owned()), entry_id));
+            url
+          }
+        };
+        urls.push(url);
+      }
+      self.write_index(id, &entries)?;
+      return Ok(urls);
+    }
+    let mut urls = Vec::with_capacity(contents.len());
+    for file in contents {
+      let name = self.write_file(&dir, file)?;
+      urls.push(PasteUrl::html(Some(PasteFileName::Explicit(file.name.clone())), self.url(id, &name)));
+    }
+    Ok(urls)
+  }
+}
+
+impl Deletes for Local {
+  fn delete(&self, id: &str) -> Result<()> {
+    let dir = self.paste_directory(id)?;
+    if !dir.is_dir() {
+      bail!("local has no paste {}", id);
+    }
+    // the pastes listed in an index are deleted first, so they are not left behind if deleting one fails
+    if let Some(entries) = self.index_entries(&self.read_files(id)?)? {
+      for (_, entry_id) in entries {
+        let entry_dir = self.paste_directory(&entry_id)?;
+        if !entry_dir.is_dir() {
+          warn!("the paste {} in the index was already deleted", entry_id);
+          continue;
+        }
+        fs::remove_dir_all(&entry_dir).chain_err(|| format!("could not delete {}", entry_dir.to_string_lossy()))?;
+      }
+    }
+    fs::remove_dir_all(&dir).chain_err(|| format!("could not delete {}", dir.to_string_lossy()))?;
+    Ok(())
+  }
+}
+
+impl HasClient for Local {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
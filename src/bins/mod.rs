@@ -5,6 +5,14 @@ pub mod pastebin;
 pub mod pastegg;
 pub mod fedora;
 pub mod bitbucket;
+pub mod privatebin;
+pub mod gitlab;
+pub mod fiche;
+pub mod filehost;
+pub mod http;
+pub mod local;
+pub mod plugin;
+mod multipart;
 
 pub use self::sprunge::Sprunge;
 pub use self::hastebin::Hastebin;
@@ -13,3 +21,154 @@ pub use self::pastebin::Pastebin;
 pub use self::pastegg::PasteGg;
 pub use self::fedora::Fedora;
 pub use self::bitbucket::Bitbucket;
+pub use self::privatebin::PrivateBin;
+pub use self::gitlab::GitLab;
+pub use self::fiche::Fiche;
+pub use self::filehost::{FileHost, FileHostKind};
+pub use self::http::Http;
+pub use self::local::Local;
+pub use self::plugin::Plugin;
+
+use {Bin, CreatesHtmlUrls, HasClient, PasteUrl};
+use error::*;
+use files::{DownloadedFile, IndexedFile, PasteFileName};
+use config::{Config, ConfigBin, CommandLineOptions};
+use serde_json;
+use url::Url;
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::Arc;
+
+/// The built-in bins, then the bins defined in the config file, by name. Bins defined in the config with the same name
+/// as a built-in bin are skipped, as are definitions that are not valid.
+pub fn configured(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> BTreeMap<String, Box<Bin>> {
+  let mut bins: BTreeMap<String, Box<Bin>> = built_in(config.clone(), cli.clone())
+    .into_iter()
+    .map(|b| (b.name().to_owned(), b))
+    .collect();
+
+  for (name, definition) in &config.bins {
+    if bins.contains_key(name) {
+      warn!("ignoring the bin {} defined in the config, since there is already a bin with that name", name);
+      continue;
+    }
+    match from_config(name, definition, config.clone(), cli.clone()) {
+      Ok(b) => {
+        bins.insert(name.clone(), b);
+      },
+      Err(e) => {
+        warn!("ignoring the bin {} defined in the config: {}", name, e);
+        for cause in e.iter().skip(1) {
+          warn!("{}", cause);
+        }
+      }
+    }
+  }
+
+  bins
+}
+
+/// The plugins on `PATH`, skipping any with the same name as one of `bins`.
+pub fn plugins(config: Arc<Config>, cli: Arc<CommandLineOptions>, bins: &BTreeMap<String, Box<Bin>>) -> Vec<Plugin> {
+  let mut plugins = Vec::new();
+  for (name, path) in plugin::discover() {
+    if bins.contains_key(&name) {
+      debug!("ignoring the plugin at {}, since there is already a bin called {}", path.to_string_lossy(), name);
+      continue;
+    }
+    plugins.push(Plugin::new(&name, path, config.clone(), cli.clone()));
+  }
+  plugins
+}
+
+/// The bins that are part of bins, using the settings in `config`.
+pub fn built_in(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Vec<Box<Bin>> {
+  vec![
+    Box::new(Sprunge::new(config.clone())),
+    Box::new(Hastebin::new(config.clone())),
+    Box::new(Fedora::new(config.clone(), cli.clone())),
+    Box::new(Gist::new(config.clone(), cli.clone())),
+    Box::new(Bitbucket::new(config.clone(), cli.clone())),
+    Box::new(Pastebin::new(config.clone(), cli.clone())),
+    Box::new(PasteGg::new(config.clone(), cli.clone())),
+    Box::new(PrivateBin::new(config.clone(), cli.clone())),
+    Box::new(GitLab::new(config.clone(), cli.clone())),
+    Box::new(Fiche::new(config.clone())),
+    Box::new(FileHost::null_pointer(config.clone(), cli.clone())),
+    Box::new(FileHost::transfer_sh(config.clone(), cli.clone())),
+    Box::new(Local::new(config.clone())),
+  ]
+}
+
+/// A bin defined under `[bins]` in the config file, which is either a custom bin or another instance of a built-in bin.
+pub fn from_config(name: &str, definition: &ConfigBin, config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Result<Box<Bin>> {
+  match definition.kind.as_ref().map(|x| x.as_str()) {
+    None | Some("http") => Http::new(name, definition, cli).map(|b| Box::new(b) as Box<Bin>),
+    Some("hastebin") => Ok(Box::new(Hastebin::named(name, &definition.hastebin()))),
+    Some("pastegg") => Ok(Box::new(PasteGg::named(name, &definition.pastegg(), config, cli))),
+    Some("privatebin") => Ok(Box::new(PrivateBin::named(name, &definition.privatebin(), cli))),
+    Some("0x0") => Ok(Box::new(FileHost::named(name, FileHostKind::NullPointer, &definition.server, config, cli))),
+    Some("transfersh") => Ok(Box::new(FileHost::named(name, FileHostKind::TransferSh, &definition.server, config, cli))),
+    Some(k) => Err(format!("{} has an unknown type \"{}\"", name, k).into())
+  }
+}
+
+/// The server a bin should use: `setting` if it is set and not empty, or `default` otherwise. Trailing slashes are
+/// removed, so paths can be added with `format!("{}/path", server)`.
+fn server(setting: &Option<String>, default: &str) -> String {
+  setting.as_ref()
+    .map(|x| x.trim())
+    .and_then(|x| if x.is_empty() { None } else { Some(x) })
+    .unwrap_or(default)
+    .trim_right_matches('/')
+    .to_owned()
+}
+
+/// The host of a server, which is used to find the bin for a URL.
+fn host(server: &str) -> String {
+  Url::parse(server).ok()
+    .and_then(|u| url_host(&u))
+    .unwrap_or_default()
+}
+
+/// The host of a URL as bins report it in `html_host` and `raw_host`: the host name, followed by the port if it is not
+/// the default for the scheme. Servers on different ports of the same host are different bins.
+pub fn url_host(url: &Url) -> Option<String> {
+  let host = option!(url.host_str());
+  Some(match url.port() {
+    Some(port) => format!("{}:{}", host, port),
+    None => host.to_owned()
+  })
+}
+
+/// Downloads the paste at `url` for bins that upload one file per paste. If the paste is an index made by `Uploads`, the
+/// files it lists are returned as URLs made by `format_url`. Otherwise the paste itself is returned, already downloaded,
+/// as `shown_url`.
+fn download_or_index<B, F>(bin: &B, id: &str, url: &str, shown_url: &str, format_url: F) -> Result<Vec<PasteUrl>>
+  where B: HasClient + CreatesHtmlUrls,
+        F: Fn(&str) -> String
+{
+  let mut res = bin.client().get(url).send()?;
+  let mut content = Vec::new();
+  res.read_to_end(&mut content)?;
+  if res.status.class().default_code() != ::hyper::Ok {
+    debug!("bad status code");
+    return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(String::from_utf8_lossy(&content).into_owned())).into());
+  }
+  let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_slice(&content);
+  let is = match parsed {
+    Ok(is) => is,
+    Err(_) => return Ok(vec![PasteUrl::Downloaded(shown_url.to_owned(), DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
+  };
+  debug!("file was an index, so checking its urls");
+  let ids: Option<Vec<(String, String)>> = is.iter().map(|x| bin.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
+  let ids = match ids {
+    Some(i) => i,
+    None => {
+      debug!("could not parse an ID from one of the URLs in the index");
+      bail!("one of the URLs in the index did not contain a valid ID");
+    }
+  };
+  Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), format_url(&id))).collect())
+}
@@ -0,0 +1,75 @@
+use hyper::header::ContentType;
+use hyper::mime::{Attr, Mime, SubLevel, TopLevel, Value};
+use rand::{Rng, thread_rng};
+
+/// A `multipart/form-data` request body, as used by file hosts that expect a browser-style upload form.
+#[derive(Debug)]
+pub struct FormData {
+  boundary: String,
+  body: Vec<u8>
+}
+
+impl FormData {
+  pub fn new() -> FormData {
+    FormData {
+      boundary: thread_rng().gen_ascii_chars().take(40).collect(),
+      body: Vec::new()
+    }
+  }
+
+  /// Adds a plain field to the form.
+  pub fn text(&mut self, name: &str, value: &str) -> &mut FormData {
+    self.add_boundary();
+    self.add_line(&format!("Content-Disposition: form-data; name=\"{}\"", escape(name)));
+    self.end_line();
+    self.add_line(value);
+    self
+  }
+
+  /// Adds a file to the form. Files that are valid UTF-8 are sent as text, and anything else as binary.
+  pub fn file(&mut self, name: &str, file_name: &str, content: &[u8]) -> &mut FormData {
+    self.add_boundary();
+    self.add_line(&format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"", escape(name), escape(file_name)));
+    if ::std::str::from_utf8(content).is_ok() {
+      self.add_line("Content-Type: text/plain; charset=utf-8");
+    } else {
+      self.add_line("Content-Type: application/octet-stream");
+    }
+    self.end_line();
+    self.body.extend_from_slice(content);
+    self.end_line();
+    self
+  }
+
+  /// The `Content-Type` header to send with the form, which includes its boundary.
+  pub fn content_type(&self) -> ContentType {
+    ContentType(Mime(TopLevel::Multipart,
+                     SubLevel::FormData,
+                     vec![(Attr::Boundary, Value::Ext(self.boundary.clone()))]))
+  }
+
+  pub fn finish(mut self) -> Vec<u8> {
+    let end = format!("--{}--", self.boundary);
+    self.add_line(&end);
+    self.body
+  }
+
+  fn add_boundary(&mut self) {
+    let boundary = format!("--{}", self.boundary);
+    self.add_line(&boundary);
+  }
+
+  fn add_line(&mut self, line: &str) {
+    self.body.extend_from_slice(line.as_bytes());
+    self.end_line();
+  }
+
+  fn end_line(&mut self) {
+    self.body.extend_from_slice(b"\r\n");
+  }
+}
+
+/// Escapes a name for use in a quoted header parameter.
+fn escape(name: &str) -> String {
+  name.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
@@ -1,39 +1,128 @@
 use url::Url;
 use url::form_urlencoded;
-use hyper::Client;
 use hyper::header::ContentType;
-use serde_json;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, Response};
 use config::{Config, CommandLineOptions};
 
 use std::io::Read;
 use std::sync::Arc;
 
+// languages whose pastebin format names differ from the names bins uses
+const FORMATS: &'static [(&'static str, &'static str)] = &[
+  ("html", "html5"),
+  ("toml", "ini")
+];
+
+// the expiry options pastebin accepts, in seconds
+const EXPIRE_DATES: &'static [(u64, &'static str)] = &[
+  (60 * 10, "10M"),
+  (60 * 60, "1H"),
+  (60 * 60 * 24, "1D"),
+  (60 * 60 * 24 * 7, "1W"),
+  (60 * 60 * 24 * 14, "2W"),
+  (60 * 60 * 24 * 30, "1M"),
+  (60 * 60 * 24 * 180, "6M"),
+  (60 * 60 * 24 * 365, "1Y")
+];
+
+const DEFAULT_SERVER: &'static str = "https://pastebin.com";
+
 pub struct Pastebin {
   config: Arc<Config>,
   cli: Arc<CommandLineOptions>,
-  client: Client
+  client: HttpClient,
+  server: String,
+  host: String
 }
 
 impl Pastebin {
   pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Pastebin {
+    let server = super::server(&config.pastebin.server, DEFAULT_SERVER);
+    let host = super::host(&server);
     Pastebin {
       config: config,
       cli: cli,
-      client: ::new_client()
+      client: ::transport::new_client(),
+      server: server,
+      host: host
     }
   }
 
   fn _format_raw_url(&self, id: &str) -> String {
-    format!("https://pastebin.com/raw/{}", id)
+    format!("{}/raw/{}", self.server, id)
   }
 
   fn _format_html_url(&self, id: &str) -> String {
-    format!("https://pastebin.com/{}", id)
+    format!("{}/{}", self.server, id)
+  }
+
+  fn api_key(&self) -> Result<&str> {
+    match self.config.pastebin.api_key {
+      Some(ref key) if !key.is_empty() => Ok(key),
+      _ => bail!("no pastebin api key set")
+    }
+  }
+
+  fn user_key(&self) -> Result<String> {
+    let config_values = (&self.config.pastebin.username, &self.config.pastebin.password);
+    let (username, password) = match config_values {
+      (&Some(ref u), &Some(ref p)) if !u.is_empty() && !p.is_empty() => (u, p),
+      _ => bail!("no pastebin username/password set")
+    };
+    debug!("logging in to pastebin");
+    let mut res = self.client.post(&format!("{}/api/api_login.php", self.server))
+      .body(&form_urlencoded::Serializer::new(String::new())
+        .append_pair("api_dev_key", self.api_key()?)
+        .append_pair("api_user_name", username)
+        .append_pair("api_user_password", password)
+        .finish())
+      .header(ContentType::form_url_encoded())
+      .send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    self.check_response(&res, content)
+  }
+
+  fn check_response(&self, res: &Response, content: String) -> Result<String> {
+    if res.status.class().default_code() != ::hyper::Ok {
+      debug!("bad status code");
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    // pastebin reports errors with a 200 status code
+    if content.starts_with("Bad API request") {
+      return Err(ErrorKind::BinError(content).into());
+    }
+    Ok(content.replace("\n", ""))
+  }
+
+  fn expire_date(&self) -> &'static str {
+    let secs = match self.cli.expires {
+      Some(s) => s,
+      None => return "N"
+    };
+    // pastebin only accepts a few durations, so use the shortest one that is at least as long as requested
+    let date = EXPIRE_DATES.iter()
+      .find(|&&(s, _)| s >= secs)
+      .or_else(|| {
+        warn!("pastebin pastes can last at most a year, so the paste will expire sooner than requested");
+        EXPIRE_DATES.last()
+      })
+      .map(|&(_, d)| d)
+      .unwrap_or("N");
+    debug!("using pastebin expiry {} for {} seconds", date, secs);
+    date
+  }
+
+  fn paste_format<'a>(&self, language: &'a str) -> &'a str {
+    match FORMATS.iter().find(|&&(lang, _)| lang == language) {
+      Some(&(_, format)) => format,
+      None => language
+    }
   }
 
   fn id_from_url(&self, url: &str) -> Option<String> {
@@ -49,11 +138,11 @@ impl Bin for Pastebin {
   }
 
   fn html_host(&self) -> &str {
-    "pastebin.com"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "pastebin.com"
+    &self.host
   }
 }
 
@@ -88,29 +177,7 @@ impl CreatesHtmlUrls for Pastebin {
 impl CreatesRawUrls for Pastebin {
   fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     let url = self._format_raw_url(id);
-    let mut res = self.client.get(&url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self._format_raw_url(&id))).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &url, &url, |id| self._format_raw_url(id))
   }
 
   fn id_from_raw_url(&self, url: &str) -> Option<String> {
@@ -120,46 +187,78 @@ impl CreatesRawUrls for Pastebin {
 
 impl HasFeatures for Pastebin {
   fn features(&self) -> Vec<BinFeature> {
-    // TODO: use pastebin's crappy login system to allow authed pastes
     vec![BinFeature::Public,
          BinFeature::Private,
+         BinFeature::Authed,
          BinFeature::Anonymous,
-         BinFeature::SingleNaming]
+         BinFeature::SingleNaming,
+         BinFeature::Deletable,
+         BinFeature::Expiring,
+         BinFeature::Titled]
   }
 }
 
 impl UploadsSingleFiles for Pastebin {
   fn upload_single(&self, contents: &UploadFile) -> Result<PasteUrl> {
     debug!("uploading single file");
-    let api_key = match self.config.pastebin.api_key {
-      Some(ref key) if !key.is_empty() => key,
-      _ => bail!("no pastebin api key set")
+    let api_key = self.api_key()?;
+    let user_key = match self.cli.authed.or(self.config.defaults.authed) {
+      Some(true) => Some(self.user_key()?),
+      _ => None
     };
-    let mut res = self.client.post("https://pastebin.com/api/api_post.php")
-      .body(&form_urlencoded::Serializer::new(String::new())
-        .append_pair("api_option", "paste")
-        .append_pair("api_paste_code", &contents.content)
-        .append_pair("api_paste_private", self.cli.private.or(self.config.defaults.private).map(|x| if x { "1" } else { "0" }).unwrap_or("0"))
-        .append_pair("api_paste_name", &contents.name)
-        .append_pair("api_dev_key", api_key)
-        .finish())
+    let mut body = form_urlencoded::Serializer::new(String::new());
+    body
+      .append_pair("api_option", "paste")
+      .append_pair("api_paste_code", contents.text()?)
+      .append_pair("api_paste_private", self.cli.private.or(self.config.defaults.private).map(|x| if x { "1" } else { "0" }).unwrap_or("0"))
+      .append_pair("api_paste_name", self.cli.title.as_ref().unwrap_or(&contents.name))
+      .append_pair("api_paste_expire_date", self.expire_date())
+      .append_pair("api_dev_key", api_key);
+    if let Some(ref key) = user_key {
+      body.append_pair("api_user_key", key);
+    }
+    if let Some(ref language) = contents.language {
+      body.append_pair("api_paste_format", self.paste_format(language));
+    }
+    let mut res = self.client.post(&format!("{}/api/api_post.php", self.server))
+      .body(&body.finish())
       .header(ContentType::form_url_encoded())
       .send()?;
     debug!("response: {:?}", res);
     let mut content = String::new();
     res.read_to_string(&mut content)?;
     debug!("content: {}", content);
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let url = content.replace("\n", "");
+    let url = self.check_response(&res, content)?;
     Ok(PasteUrl::html(Some(PasteFileName::Explicit(contents.name.clone())), url))
   }
 }
 
+impl Updates for Pastebin {}
+
+impl Deletes for Pastebin {
+  fn delete(&self, id: &str) -> Result<()> {
+    let user_key = self.user_key()?;
+    let mut res = self.client.post(&format!("{}/api/api_post.php", self.server))
+      .body(&form_urlencoded::Serializer::new(String::new())
+        .append_pair("api_option", "delete")
+        .append_pair("api_dev_key", self.api_key()?)
+        .append_pair("api_user_key", &user_key)
+        .append_pair("api_paste_key", id)
+        .finish())
+      .header(ContentType::form_url_encoded())
+      .send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    self.check_response(&res, content).map(|_| ())
+  }
+}
+
 impl HasClient for Pastebin {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
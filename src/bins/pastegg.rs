@@ -1,43 +1,64 @@
 use url::Url;
 use uuid::Uuid;
-use hyper::Client;
-use hyper::client::RequestBuilder;
 use hyper::header::{Headers, ContentType, UserAgent, Authorization};
 use serde_json;
+use base64;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
-use config::{Config, CommandLineOptions};
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, RequestBuilder};
+use config::{Config, ConfigPasteGg, CommandLineOptions};
 
 use std::io::Read;
 use std::sync::Arc;
 
+const DEFAULT_SERVER: &'static str = "https://paste.gg";
+
 pub struct PasteGg {
+  name: String,
   config: Arc<Config>,
   cli: Arc<CommandLineOptions>,
-  client: Client
+  client: HttpClient,
+  key: Option<String>,
+  server: String,
+  api_server: String,
+  host: String
 }
 
 impl PasteGg {
   pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> PasteGg {
+    let settings = config.pastegg.clone();
+    PasteGg::named("pastegg", &settings, config, cli)
+  }
+
+  /// Creates a paste.gg bin with its own name, server and key, so more than one paste.gg instance can be used at once.
+  pub fn named(name: &str, settings: &ConfigPasteGg, config: Arc<Config>, cli: Arc<CommandLineOptions>) -> PasteGg {
+    let server = super::server(&settings.server, DEFAULT_SERVER);
+    let host = super::host(&server);
+    // paste.gg serves its api from the api subdomain of the site
+    let scheme = Url::parse(&server).map(|u| u.scheme().to_owned()).unwrap_or_else(|_| String::from("https"));
+    let api_server = super::server(&settings.api_server, &format!("{}://api.{}", scheme, host));
     PasteGg {
+      name: name.to_owned(),
       config: config,
       cli: cli,
-      client: ::new_client()
+      client: ::transport::new_client(),
+      key: settings.key.clone().and_then(|x| if x.is_empty() { None } else { Some(x) }),
+      server: server,
+      api_server: api_server,
+      host: host
     }
   }
 
   fn add_headers<'a>(&self, rb: RequestBuilder<'a>) -> RequestBuilder<'a> {
     let mut headers = Headers::new();
     headers.set(ContentType::json());
-    headers.set(UserAgent(format!("bins/{}", crate_version!())));
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
     if let Some(true) = self.cli.authed.or(self.config.defaults.authed) {
-      if let Some(ref key) = self.config.pastegg.key {
-        if !key.is_empty() {
-          headers.set(Authorization(format!("Key {}", key)));
-        }
+      if let Some(ref key) = self.key {
+        headers.set(Authorization(format!("Key {}", key)));
       }
     }
     rb.headers(headers)
@@ -45,7 +66,7 @@ impl PasteGg {
 
   fn get_paste(&self, id: &str) -> Result<PasteGgPaste<FullPasteGgFile>> {
     debug!("getting paste for ID {}", id);
-    let builder = self.client.get(&format!("https://api.paste.gg/v1/pastes/{}?full=true", id));
+    let builder = self.client.get(&format!("{}/v1/pastes/{}?full=true", self.api_server, id));
     let mut res = self.add_headers(builder).send()?;
     let mut content = String::new();
     res.read_to_string(&mut content)?;
@@ -71,15 +92,15 @@ impl PasteGg {
 
 impl Bin for PasteGg {
   fn name(&self) -> &str {
-    "pastegg"
+    &self.name
   }
 
   fn html_host(&self) -> &str {
-    "paste.gg"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "paste.gg"
+    &self.host
   }
 }
 
@@ -107,7 +128,7 @@ impl CreatesHtmlUrls for PasteGg {
     let urls: Vec<PasteUrl> = paste.files.iter()
       .map(|file| PasteUrl::html(
         file.name.clone().map(PasteFileName::Explicit),
-        format!("https://paste.gg/{}", id)
+        format!("{}/{}", self.server, id)
       ))
       .collect();
     Ok(urls)
@@ -124,7 +145,7 @@ impl CreatesRawUrls for PasteGg {
     let urls: Vec<PasteUrl> = paste.files.iter()
       .map(|file| PasteUrl::raw(
         file.name.clone().map(PasteFileName::Explicit),
-        format!("https://api.paste.gg/v1/pastes/{}/files/{}/raw", paste.id, file.id)
+        format!("{}/v1/pastes/{}/files/{}/raw", self.api_server, paste.id, file.id)
       ))
       .collect();
     Ok(urls)
@@ -150,6 +171,12 @@ impl HasFeatures for PasteGg {
       BinFeature::Anonymous,
       BinFeature::MultiFile,
       BinFeature::SingleNaming,
+      BinFeature::Deletable,
+      BinFeature::Updatable,
+      BinFeature::BinaryFiles,
+      BinFeature::Expiring,
+      BinFeature::Titled,
+      BinFeature::Described,
     ]
   }
 }
@@ -160,7 +187,8 @@ impl Uploads for PasteGg {
       .iter()
       .map(|file| PasteGgUploadFile {
         name: Some(file.name.clone()),
-        content: PasteGgContent::Text(file.content.clone()),
+        highlight_language: file.language.clone(),
+        content: PasteGgContent::from_file(file),
       })
       .collect();
     let visibility = if self.cli.private.or(self.config.defaults.private).map(|x| !x).unwrap_or(false) {
@@ -168,14 +196,17 @@ impl Uploads for PasteGg {
     } else {
       Visibility::Unlisted
     };
+    let expires = self.cli.expires
+      .map(|secs| format!("{}", (::time::now_utc() + ::time::Duration::seconds(secs as i64)).rfc3339()));
     let upload_file = PasteGgUpload {
-      name: None,
-      description: None,
+      name: self.cli.title.clone(),
+      description: self.cli.description.clone(),
       visibility,
+      expires,
       files,
     };
     let upload_json = serde_json::to_string(&upload_file)?;
-    let builder = self.client.post("https://api.paste.gg/v1/pastes").body(&upload_json);
+    let builder = self.client.post(&format!("{}/v1/pastes", self.api_server)).body(&upload_json);
     let mut res = self.add_headers(builder).send()?;
     let mut content = String::new();
     res.read_to_string(&mut content)?;
@@ -194,14 +225,75 @@ impl Uploads for PasteGg {
         return Err(ErrorKind::BinError(msg).into())
       },
     };
-    Ok(vec![PasteUrl::html(None, format!("https://paste.gg/{}", paste.id))])
+    let url = PasteUrl::html(None, format!("{}/{}", self.server, paste.id));
+    match paste.deletion_key {
+      Some(key) => Ok(vec![url.with_deletion_key(key)]),
+      None => Ok(vec![url])
+    }
+  }
+}
+
+impl Updates for PasteGg {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let paste = self.get_paste(id)?;
+    for file in contents {
+      let upload_file = PasteGgUploadFile {
+        name: Some(file.name.clone()),
+        highlight_language: file.language.clone(),
+        content: PasteGgContent::from_file(file),
+      };
+      let upload_json = serde_json::to_string(&upload_file)?;
+      let existing = paste.files.iter().find(|f| f.name.as_ref() == Some(&file.name));
+      let builder = match existing {
+        Some(f) => {
+          debug!("replacing file {} ({})", file.name, f.id);
+          self.client.patch(&format!("{}/v1/pastes/{}/files/{}", self.api_server, paste.id, f.id))
+        },
+        None => {
+          debug!("adding file {}", file.name);
+          self.client.post(&format!("{}/v1/pastes/{}/files", self.api_server, paste.id))
+        }
+      };
+      let mut res = self.add_headers(builder.body(&upload_json)).send()?;
+      let mut content = String::new();
+      res.read_to_string(&mut content)?;
+      if res.status.class().default_code() != ::hyper::Ok {
+        debug!("bad status code");
+        return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+      }
+    }
+    Ok(vec![PasteUrl::html(None, format!("{}/{}", self.server, paste.id))])
+  }
+}
+
+impl Deletes for PasteGg {
+  fn delete(&self, id: &str) -> Result<()> {
+    let key = match (&self.cli.deletion_key, &self.key) {
+      (&Some(ref k), _) if !k.is_empty() => k,
+      (_, &Some(ref k)) if !k.is_empty() => k,
+      _ => bail!("a deletion key (--key) or a paste.gg api key must be set to delete pastes")
+    };
+    let mut res = self.client.delete(&format!("{}/v1/pastes/{}", self.api_server, id))
+      .header(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))))
+      .header(Authorization(format!("Key {}", key)))
+      .send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status != ::hyper::status::StatusCode::NoContent {
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    Ok(())
   }
 }
 
 impl HasClient for PasteGg {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
 
 #[derive(Debug, Deserialize)]
@@ -223,6 +315,7 @@ struct PasteGgPaste<T> {
   description: Option<String>,
   visibility: Visibility,
   files: Vec<T>,
+  deletion_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -248,6 +341,15 @@ enum PasteGgContent {
   Xz(String),
 }
 
+impl PasteGgContent {
+  fn from_file(file: &UploadFile) -> PasteGgContent {
+    match String::from_utf8(file.content.clone()) {
+      Ok(s) => PasteGgContent::Text(s),
+      Err(_) => PasteGgContent::Base64(base64::encode(&file.content)),
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum Visibility {
@@ -261,11 +363,15 @@ struct PasteGgUpload {
   name: Option<String>,
   description: Option<String>,
   visibility: Visibility,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  expires: Option<String>,
   files: Vec<PasteGgUploadFile>,
 }
 
 #[derive(Debug, Serialize)]
 struct PasteGgUploadFile {
   name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  highlight_language: Option<String>,
   content: PasteGgContent,
 }
@@ -0,0 +1,393 @@
+//! Bins provided by external programs.
+//!
+//! Any executable called `bins-<name>` on `PATH` is used as a bin called `<name>`. For every operation, bins runs the
+//! program, writes one JSON request to its stdin and reads one JSON response from its stdout. Anything the program
+//! writes to stderr is shown to the user. Plugins are only run when they are used, so finding them is cheap.
+//!
+//! Every request has a `command`:
+//!
+//! - `info` - describe the bin. Respond with `{"html_host": "...", "raw_host": "...", "features": [...]}`, where
+//!   `features` are names such as `"public"`, `"private"`, `"multi_file"` or `"binary_files"`.
+//! - `upload` - upload `files`, given as `{"name": "...", "content": ..., "language": "..."}`. Respond with
+//!   `{"urls": [...]}`.
+//! - `update` - add or replace `files` in the paste with the given `id`. Respond with `{"urls": [...]}`.
+//! - `delete` - delete the paste with the given `id`, using `deletion_key` if there is one. Respond with `{}`.
+//! - `html_urls` and `raw_urls` - list the URLs of the files in the paste with the given `id`. Respond with
+//!   `{"urls": [...]}`.
+//! - `id_from_url` - find the ID of the paste at `url`, which is an `html` or `raw` URL depending on `kind`. Respond
+//!   with `{"id": "..."}`, or `{"id": null}` if the URL is not for a paste.
+//!
+//! `upload` and `update` requests also have `options`, with `private`, `authed`, `title`, `description`, `expires` (in
+//! seconds) and `burn`, which are `null` if they were not set.
+//!
+//! File content is a string if it is valid UTF-8, or `{"base64": "..."}` otherwise. URLs are given as
+//! `{"html": {"name": ..., "url": "..."}}` or `{"raw": {"name": ..., "url": "..."}}`, where `name` is
+//! `{"explicit": "..."}`, `{"guessed": "..."}` or `null`, and may have a `deletion_key`. Raw URLs may instead be
+//! `{"downloaded": ["url", {"name": ..., "content": ...}]}` if the file has already been downloaded.
+//!
+//! Any request can fail by responding with `{"error": "message"}`.
+
+use lazycell::AtomicLazyCell;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::{Config, CommandLineOptions};
+
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+const PREFIX: &'static str = "bins-";
+
+/// Executables that start with `PREFIX` but are not plugins, such as the paste server installed alongside bins. They are
+/// never run, but would otherwise be listed as bins.
+const NOT_PLUGINS: &'static [&'static str] = &["server"];
+
+pub struct Plugin {
+  name: String,
+  path: PathBuf,
+  config: Arc<Config>,
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  info: AtomicLazyCell<PluginInfo>,
+  /// Held while the plugin is asked for its info, so it is only run once.
+  info_lock: Mutex<()>
+}
+
+impl Plugin {
+  /// Creates a bin for the plugin at `path`. The plugin is not run until it is used.
+  pub fn new(name: &str, path: PathBuf, config: Arc<Config>, cli: Arc<CommandLineOptions>) -> Plugin {
+    Plugin {
+      name: name.to_owned(),
+      path: path,
+      config: config,
+      cli: cli,
+      client: ::transport::new_client(),
+      info: AtomicLazyCell::new(),
+      info_lock: Mutex::new(())
+    }
+  }
+
+  /// The plugin's response to `info`, which is asked for the first time it is needed. A plugin that cannot describe
+  /// itself has no hosts or features.
+  fn info(&self) -> &PluginInfo {
+    if let Some(info) = self.info.borrow() {
+      return info;
+    }
+    {
+      // a poisoned lock only means another thread panicked while asking, so ask again
+      let _guard = self.info_lock.lock().unwrap_or_else(|e| e.into_inner());
+      if !self.info.filled() {
+        let info = match call(&self.path, &PluginRequest::Info).chain_err(|| format!("could not load the {} plugin", self.name)) {
+          Ok(i) => i,
+          Err(e) => {
+            warn!("{}", e);
+            for cause in e.iter().skip(1) {
+              warn!("{}", cause);
+            }
+            PluginInfo::default()
+          }
+        };
+        // only this thread fills the cell, since it holds the lock
+        let _ = self.info.fill(info);
+      }
+    }
+    match self.info.borrow() {
+      Some(info) => info,
+      None => unreachable!("plugin info is filled before the lock is released")
+    }
+  }
+
+  fn call<T: DeserializeOwned>(&self, request: &PluginRequest) -> Result<T> {
+    call(&self.path, request).chain_err(|| format!("the {} plugin failed", self.name))
+  }
+
+  fn options(&self) -> PluginOptions {
+    PluginOptions {
+      private: self.cli.private.or(self.config.defaults.private),
+      authed: self.cli.authed.or(self.config.defaults.authed),
+      title: self.cli.title.as_ref().map(|x| x.as_str()),
+      description: self.cli.description.as_ref().map(|x| x.as_str()),
+      expires: self.cli.expires,
+      burn: self.cli.burn
+    }
+  }
+
+  fn id_from_url(&self, url: &str, kind: &'static str) -> Option<String> {
+    match self.call::<IdResponse>(&PluginRequest::IdFromUrl { url: url, kind: kind }) {
+      Ok(r) => r.id,
+      Err(e) => {
+        debug!("{}", e);
+        None
+      }
+    }
+  }
+}
+
+impl Bin for Plugin {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn html_host(&self) -> &str {
+    &self.info().html_host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.info().raw_host
+  }
+}
+
+impl ManagesUrls for Plugin {}
+
+impl CreatesUrls for Plugin {}
+
+impl FormatsUrls for Plugin {}
+
+impl FormatsHtmlUrls for Plugin {
+  fn format_html_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl FormatsRawUrls for Plugin {
+  fn format_raw_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl CreatesHtmlUrls for Plugin {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    self.call::<UrlsResponse>(&PluginRequest::HtmlUrls { id: id }).map(|r| r.urls)
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url, "html")
+  }
+}
+
+impl CreatesRawUrls for Plugin {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    self.call::<UrlsResponse>(&PluginRequest::RawUrls { id: id }).map(|r| r.urls)
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url, "raw")
+  }
+}
+
+impl HasFeatures for Plugin {
+  fn features(&self) -> Vec<BinFeature> {
+    self.info().features.iter()
+      .filter_map(|f| match serde_json::from_value(Value::String(f.clone())) {
+        Ok(feature) => Some(feature),
+        Err(_) => {
+          debug!("ignoring unknown feature {} from the {} plugin", f, self.name);
+          None
+        }
+      })
+      .collect()
+  }
+}
+
+impl Uploads for Plugin {
+  fn upload(&self, contents: &[UploadFile], index: bool) -> Result<Vec<PasteUrl>> {
+    let request = PluginRequest::Upload {
+      files: contents,
+      index: index,
+      options: self.options()
+    };
+    self.call::<UrlsResponse>(&request).map(|r| r.urls)
+  }
+}
+
+impl Updates for Plugin {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let request = PluginRequest::Update {
+      id: id,
+      files: contents,
+      options: self.options()
+    };
+    self.call::<UrlsResponse>(&request).map(|r| r.urls)
+  }
+}
+
+impl Deletes for Plugin {
+  fn delete(&self, id: &str) -> Result<()> {
+    let request = PluginRequest::Delete {
+      id: id,
+      deletion_key: self.cli.deletion_key.as_ref().map(|x| x.as_str())
+    };
+    self.call::<Value>(&request).map(|_| ())
+  }
+}
+
+impl HasClient for Plugin {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
+
+/// Finds every plugin on `PATH`, returning the name and path of each. If two plugins have the same name, the one that
+/// comes first on `PATH` is used.
+pub fn discover() -> Vec<(String, PathBuf)> {
+  let paths = match env::var_os("PATH") {
+    Some(p) => p,
+    None => return Vec::new()
+  };
+  let mut plugins: Vec<(String, PathBuf)> = Vec::new();
+  for dir in env::split_paths(&paths) {
+    let entries = match dir.read_dir() {
+      Ok(e) => e,
+      Err(_) => continue
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+      let path = entry.path();
+      let name = match plugin_name(&path) {
+        Some(n) => n,
+        None => continue
+      };
+      if !is_executable(&path) || plugins.iter().any(|&(ref n, _)| *n == name) {
+        continue;
+      }
+      debug!("found plugin {} at {}", name, path.to_string_lossy());
+      plugins.push((name, path));
+    }
+  }
+  plugins
+}
+
+fn plugin_name(path: &Path) -> Option<String> {
+  // on windows, plugins have an extension that is not part of their name
+  let name = if cfg!(windows) { path.file_stem() } else { path.file_name() };
+  let name = option!(name.and_then(|x| x.to_str()));
+  if !name.starts_with(PREFIX) || name.len() == PREFIX.len() {
+    return None;
+  }
+  let name = &name[PREFIX.len()..];
+  if NOT_PLUGINS.contains(&name) {
+    None
+  } else {
+    Some(name.to_owned())
+  }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+  use std::os::unix::fs::PermissionsExt;
+  path.metadata().map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+  let extension = path.extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase());
+  path.is_file() && match extension.as_ref().map(|x| x.as_str()) {
+    Some("exe") | Some("bat") | Some("cmd") => true,
+    _ => false
+  }
+}
+
+/// Runs a plugin with one request and parses its response.
+fn call<T: DeserializeOwned>(path: &Path, request: &PluginRequest) -> Result<T> {
+  let request_json = serde_json::to_vec(request)?;
+  let mut child = Command::new(path)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::inherit())
+    .spawn()
+    .chain_err(|| format!("could not run {}", path.to_string_lossy()))?;
+  {
+    let stdin = child.stdin.as_mut().ok_or_else(|| ErrorKind::Msg("could not write to plugin".into()))?;
+    stdin.write_all(&request_json).chain_err(|| "could not write to plugin")?;
+  }
+  let output = child.wait_with_output().chain_err(|| "could not read from plugin")?;
+  let response: PluginResponse<T> = match serde_json::from_slice(&output.stdout) {
+    Ok(r) => r,
+    Err(e) => {
+      if !output.status.success() {
+        bail!("plugin exited with {}", output.status);
+      }
+      return Err(e).chain_err(|| "plugin responded with invalid JSON");
+    }
+  };
+  match response {
+    PluginResponse::Error { error } => Err(ErrorKind::BinError(error).into()),
+    PluginResponse::Success(t) => Ok(t)
+  }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum PluginRequest<'a> {
+  Info,
+  Upload {
+    files: &'a [UploadFile],
+    index: bool,
+    options: PluginOptions<'a>
+  },
+  Update {
+    id: &'a str,
+    files: &'a [UploadFile],
+    options: PluginOptions<'a>
+  },
+  Delete {
+    id: &'a str,
+    deletion_key: Option<&'a str>
+  },
+  HtmlUrls {
+    id: &'a str
+  },
+  RawUrls {
+    id: &'a str
+  },
+  IdFromUrl {
+    url: &'a str,
+    kind: &'static str
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct PluginOptions<'a> {
+  private: Option<bool>,
+  authed: Option<bool>,
+  title: Option<&'a str>,
+  description: Option<&'a str>,
+  expires: Option<u64>,
+  burn: Option<bool>
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PluginResponse<T> {
+  Error { error: String },
+  Success(T)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PluginInfo {
+  html_host: String,
+  raw_host: String,
+  #[serde(default)]
+  features: Vec<String>
+}
+
+#[derive(Debug, Deserialize)]
+struct UrlsResponse {
+  urls: Vec<PasteUrl>
+}
+
+#[derive(Debug, Deserialize)]
+struct IdResponse {
+  id: Option<String>
+}
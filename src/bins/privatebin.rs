@@ -0,0 +1,535 @@
+use aes::Aes256;
+use aes_gcm::AesGcm;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::generic_array::typenum::U16;
+use base64;
+use bs58;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use hyper::header::{Headers, ContentType, UserAgent};
+use ring::digest::SHA256;
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{self, Value};
+use url::Url;
+
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::{HttpClient, RequestBuilder};
+use config::{Config, ConfigPrivateBin, CommandLineOptions};
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+const DEFAULT_SERVER: &'static str = "https://privatebin.net/";
+
+// the parameters PrivateBin's own client uses for new pastes
+const ITERATIONS: u32 = 100000;
+const KEY_SIZE: usize = 32;
+const IV_SIZE: usize = 16;
+const SALT_SIZE: usize = 8;
+const TAG_BITS: u32 = 128;
+// the iteration count comes from the server, so a malicious one could otherwise make key derivation take forever
+const MAX_ITERATIONS: u32 = 1000000;
+
+/// AES-256-GCM with the 128-bit IVs PrivateBin's client uses.
+type PasteCipher = AesGcm<Aes256, U16>;
+
+// the expiry options PrivateBin accepts, in seconds
+const EXPIRE_DATES: &'static [(u64, &'static str)] = &[
+  (60 * 5, "5min"),
+  (60 * 10, "10min"),
+  (60 * 60, "1hour"),
+  (60 * 60 * 24, "1day"),
+  (60 * 60 * 24 * 7, "1week"),
+  (60 * 60 * 24 * 30, "1month"),
+  (60 * 60 * 24 * 365, "1year")
+];
+
+pub struct PrivateBin {
+  name: String,
+  cli: Arc<CommandLineOptions>,
+  client: HttpClient,
+  server: String,
+  host: String
+}
+
+impl PrivateBin {
+  pub fn new(config: Arc<Config>, cli: Arc<CommandLineOptions>) -> PrivateBin {
+    PrivateBin::named("privatebin", &config.privatebin, cli)
+  }
+
+  /// Creates a PrivateBin bin with its own name and server, so more than one instance can be used at once.
+  pub fn named(name: &str, settings: &ConfigPrivateBin, cli: Arc<CommandLineOptions>) -> PrivateBin {
+    let mut server = settings.server.clone()
+      .and_then(|x| if x.trim().is_empty() { None } else { Some(x) })
+      .unwrap_or_else(|| DEFAULT_SERVER.to_owned());
+    if !server.ends_with('/') {
+      server.push('/');
+    }
+    let host = super::host(&server);
+    PrivateBin {
+      name: name.to_owned(),
+      cli: cli,
+      client: ::transport::new_client(),
+      server: server,
+      host: host
+    }
+  }
+
+  fn add_headers<'a>(&self, rb: RequestBuilder<'a>) -> RequestBuilder<'a> {
+    let mut headers = Headers::new();
+    headers.set(ContentType::json());
+    headers.set(UserAgent(format!("bins/{}", env!("CARGO_PKG_VERSION"))));
+    // PrivateBin only answers with JSON to requests that say they are from its own client
+    headers.set_raw("X-Requested-With", vec![b"JSONHttpRequest".to_vec()]);
+    rb.headers(headers)
+  }
+
+  fn send(&self, rb: RequestBuilder) -> Result<PrivateBinResponse> {
+    let mut res = self.add_headers(rb).send()?;
+    let mut content = String::new();
+    res.read_to_string(&mut content)?;
+    if res.status.class().default_code() != ::hyper::Ok {
+      debug!("bad status code");
+      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
+    }
+    let response: PrivateBinResponse = serde_json::from_str(&content)
+      .chain_err(|| ErrorKind::InvalidResponse)?;
+    if response.status != 0 {
+      let message = response.message.unwrap_or_else(|| String::from("unknown error"));
+      return Err(ErrorKind::BinError(message).into());
+    }
+    Ok(response)
+  }
+
+  fn format_url(&self, paste_id: &str, key: &str) -> String {
+    format!("{}?{}#{}", self.server, paste_id, key)
+  }
+
+  fn expire_date(&self) -> Option<&'static str> {
+    let secs = match self.cli.expires {
+      Some(s) => s,
+      None => return None
+    };
+    // PrivateBin only accepts a few durations, so use the shortest one that is at least as long as requested
+    let date = EXPIRE_DATES.iter()
+      .find(|&&(s, _)| s >= secs)
+      .or_else(|| {
+        warn!("PrivateBin pastes can last at most a year, so the paste will expire sooner than requested");
+        EXPIRE_DATES.last()
+      })
+      .map(|&(_, d)| d);
+    debug!("using privatebin expiry {:?} for {} seconds", date, secs);
+    date
+  }
+
+  /// Derives the key used for AES from the key in the URL and the password, if any.
+  fn derive_key(&self, key: &[u8], salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut password = key.to_vec();
+    if let Some(ref p) = self.cli.password {
+      password.extend(p.as_bytes());
+    }
+    let mut derived = vec![0; KEY_SIZE];
+    pbkdf2::derive(&SHA256, iterations, salt, &password, &mut derived);
+    derived
+  }
+
+  fn encrypt(&self, file: &UploadFile) -> Result<(PrivateBinUpload, Vec<u8>)> {
+    let key = random_bytes(KEY_SIZE)?;
+    let iv = random_bytes(IV_SIZE)?;
+    let salt = random_bytes(SALT_SIZE)?;
+
+    let format = match file.language.as_ref().map(|x| x.as_str()) {
+      Some("markdown") => "markdown",
+      Some(_) => "syntaxhighlighting",
+      None => "plaintext"
+    };
+    let adata = json!([
+      [
+        base64::encode(&iv),
+        base64::encode(&salt),
+        ITERATIONS,
+        KEY_SIZE * 8,
+        TAG_BITS,
+        "aes",
+        "gcm",
+        "zlib"
+      ],
+      format,
+      self.cli.discussion.unwrap_or(false) as u8,
+      self.cli.burn.unwrap_or(false) as u8
+    ]);
+
+    let content = if file.is_binary() {
+      PrivateBinContent {
+        paste: String::new(),
+        attachment: Some(format!("data:application/octet-stream;base64,{}", base64::encode(&file.content))),
+        attachment_name: Some(file.name.clone())
+      }
+    } else {
+      PrivateBinContent {
+        paste: file.text()?.to_owned(),
+        attachment: None,
+        attachment_name: None
+      }
+    };
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::Default);
+    encoder.write_all(&serde_json::to_vec(&content)?)?;
+    let compressed = encoder.finish()?;
+
+    // the authenticated data is the exact JSON of adata that is sent with the paste
+    let aad = serde_json::to_string(&adata)?;
+    let cipher = PasteCipher::new_from_slice(&self.derive_key(&key, &salt, ITERATIONS))
+      .map_err(|_| ErrorKind::Msg("invalid privatebin key".into()))?;
+    let ct = cipher.encrypt(GenericArray::from_slice(&iv), Payload { msg: &compressed, aad: aad.as_bytes() })
+      .map_err(|_| ErrorKind::Msg(format!("could not encrypt {}", file.name)))?;
+
+    let upload = PrivateBinUpload {
+      v: 2,
+      adata: adata,
+      ct: base64::encode(&ct),
+      meta: PrivateBinMeta {
+        expire: self.expire_date()
+      }
+    };
+    Ok((upload, key))
+  }
+
+  fn decrypt(&self, paste: &PrivateBinResponse, key: &[u8]) -> Result<PrivateBinContent> {
+    if paste.v != Some(2) {
+      bail!("only version 2 PrivateBin pastes are supported");
+    }
+    let (adata, ct) = match (paste.adata.as_ref(), paste.ct.as_ref()) {
+      (Some(a), Some(c)) => (a, c),
+      _ => bail!("the PrivateBin response did not contain a paste")
+    };
+    let spec = adata.get(0)
+      .and_then(|x| x.as_array())
+      .ok_or_else(|| ErrorKind::Msg("the paste's encryption parameters are missing".into()))?;
+    let string = |i: usize| spec.get(i).and_then(|x| x.as_str()).unwrap_or_default();
+    if string(5) != "aes" || string(6) != "gcm" {
+      bail!("the paste is encrypted with {}-{}, which is not supported", string(5), string(6));
+    }
+    let iv = base64::decode(string(0)).chain_err(|| "the paste's IV is not valid base64")?;
+    let salt = base64::decode(string(1)).chain_err(|| "the paste's salt is not valid base64")?;
+    let iterations = spec.get(2).and_then(|x| x.as_u64()).unwrap_or(ITERATIONS as u64);
+    if iterations == 0 || iterations > MAX_ITERATIONS as u64 {
+      bail!("the paste's key is derived with {} iterations, but at most {} are allowed", iterations, MAX_ITERATIONS);
+    }
+    let iterations = iterations as u32;
+    if iv.len() != IV_SIZE || spec.get(3).and_then(|x| x.as_u64()) != Some(KEY_SIZE as u64 * 8) || spec.get(4).and_then(|x| x.as_u64()) != Some(TAG_BITS as u64) {
+      bail!("the paste uses encryption parameters that are not supported");
+    }
+
+    let aad = serde_json::to_string(adata)?;
+    let ct = base64::decode(ct).chain_err(|| "the paste's content is not valid base64")?;
+    let cipher = PasteCipher::new_from_slice(&self.derive_key(key, &salt, iterations))
+      .map_err(|_| ErrorKind::Msg("invalid privatebin key".into()))?;
+    let decrypted = cipher.decrypt(GenericArray::from_slice(&iv), Payload { msg: &ct, aad: aad.as_bytes() })
+      .map_err(|_| ErrorKind::Msg("could not decrypt the paste. the key or password may be wrong".into()))?;
+
+    let data = match string(7) {
+      "zlib" => {
+        let mut inflated = Vec::new();
+        DeflateDecoder::new(&decrypted[..]).read_to_end(&mut inflated)?;
+        inflated
+      },
+      "none" => decrypted,
+      c => bail!("the paste uses {} compression, which is not supported", c)
+    };
+    serde_json::from_slice(&data).chain_err(|| "could not parse the decrypted paste")
+  }
+
+  /// Downloads and decrypts a paste, returning its text and attachment as separate files.
+  fn get_paste(&self, id: &str) -> Result<Vec<DownloadedFile>> {
+    let (paste_id, key) = split_id(id)?;
+    debug!("getting paste for ID {}", paste_id);
+    let builder = self.client.get(&format!("{}?pasteid={}", self.server, paste_id));
+    let response = self.send(builder)?;
+    let content = self.decrypt(&response, &key)?;
+
+    let mut files = Vec::with_capacity(2);
+    if !content.paste.is_empty() {
+      files.push(DownloadedFile::new(PasteFileName::Guessed(paste_id.to_owned()), content.paste.into_bytes()));
+    }
+    if let Some(attachment) = content.attachment {
+      let data = match attachment.find(',') {
+        Some(i) if attachment[..i].ends_with(";base64") => base64::decode(&attachment[i + 1..])
+          .chain_err(|| "the paste's attachment is not valid base64")?,
+        Some(i) => attachment[i + 1..].as_bytes().to_vec(),
+        None => bail!("the paste's attachment is not a data URL")
+      };
+      let name = content.attachment_name
+        .map(PasteFileName::Explicit)
+        .unwrap_or_else(|| PasteFileName::Guessed(paste_id.to_owned()));
+      files.push(DownloadedFile::new(name, data));
+    }
+    Ok(files)
+  }
+}
+
+impl Bin for PrivateBin {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for PrivateBin {}
+
+impl CreatesUrls for PrivateBin {}
+
+impl FormatsUrls for PrivateBin {}
+
+impl FormatsHtmlUrls for PrivateBin {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    let (paste_id, key) = option!(id.find('#').map(|i| (&id[..i], &id[i + 1..])));
+    Some(self.format_url(paste_id, key))
+  }
+}
+
+impl FormatsRawUrls for PrivateBin {
+  fn format_raw_url(&self, _: &str) -> Option<String> {
+    None
+  }
+}
+
+impl CreatesHtmlUrls for PrivateBin {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let url = self.format_html_url(id).ok_or_else(|| ErrorKind::Msg("the privatebin url is missing its key".into()))?;
+    Ok(vec![PasteUrl::html(None, url)])
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    let url = option!(Url::parse(url).ok());
+    let query = option!(url.query());
+    let paste_id = if query.starts_with("pasteid=") {
+      &query["pasteid=".len()..]
+    } else {
+      query
+    };
+    // pastes that burn after reading have a leading - in their fragment, so PrivateBin asks before opening them
+    match url.fragment() {
+      Some(key) => Some(format!("{}#{}", paste_id, key.trim_left_matches('-'))),
+      None => Some(paste_id.to_owned())
+    }
+  }
+}
+
+impl CreatesRawUrls for PrivateBin {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    debug!("creating raw url for {}", id);
+    let url = self.format_html_url(id).unwrap_or_default();
+    let files = self.get_paste(id)?;
+    if files.len() == 1 {
+      let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_slice(&files[0].content);
+      if let Ok(is) = parsed {
+        debug!("file was an index, so downloading its urls");
+        let mut urls = Vec::with_capacity(is.len());
+        for indexed in is {
+          let id = self.id_from_html_url(&indexed.url)
+            .ok_or_else(|| ErrorKind::Msg("one of the URLs in the index did not contain a valid ID".into()))?;
+          for mut file in self.get_paste(&id)? {
+            file.name = PasteFileName::Explicit(indexed.name.clone());
+            urls.push(PasteUrl::Downloaded(indexed.url.clone(), file));
+          }
+        }
+        return Ok(urls);
+      }
+    }
+    Ok(files.into_iter().map(|f| PasteUrl::Downloaded(url.clone(), f)).collect())
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_html_url(url)
+  }
+}
+
+impl HasFeatures for PrivateBin {
+  fn features(&self) -> Vec<BinFeature> {
+    vec![
+      BinFeature::Private,
+      BinFeature::Anonymous,
+      BinFeature::Deletable,
+      BinFeature::BinaryFiles,
+      BinFeature::Expiring,
+      BinFeature::BurnAfterReading,
+      BinFeature::PasswordProtected,
+      BinFeature::Discussion,
+      BinFeature::Encrypted,
+    ]
+  }
+}
+
+impl UploadsSingleFiles for PrivateBin {
+  fn upload_single(&self, file: &UploadFile) -> Result<PasteUrl> {
+    debug!("uploading single file");
+    let (upload, key) = self.encrypt(file)?;
+    let upload_json = serde_json::to_string(&upload)?;
+    let builder = self.client.post(&self.server).body(&upload_json);
+    let response = self.send(builder)?;
+    let paste_id = response.id.ok_or_else(|| ErrorKind::InvalidResponse)?;
+    let url = PasteUrl::html(
+      Some(PasteFileName::Explicit(file.name.clone())),
+      self.format_url(&paste_id, &bs58::encode(&key).into_string())
+    );
+    match response.deletetoken {
+      Some(token) => Ok(url.with_deletion_key(token)),
+      None => Ok(url)
+    }
+  }
+}
+
+impl Updates for PrivateBin {}
+
+impl Deletes for PrivateBin {
+  fn delete(&self, id: &str) -> Result<()> {
+    let token = match self.cli.deletion_key {
+      Some(ref k) if !k.is_empty() => k,
+      _ => bail!("the deletion token printed when the paste was uploaded must be given with --key to delete pastes")
+    };
+    let paste_id = id.split('#').next().unwrap_or_default();
+    let body = serde_json::to_string(&json!({
+      "pasteid": paste_id,
+      "deletetoken": token
+    }))?;
+    self.send(self.client.post(&self.server).body(&body))?;
+    Ok(())
+  }
+}
+
+impl HasClient for PrivateBin {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
+
+fn random_bytes(len: usize) -> Result<Vec<u8>> {
+  let mut bytes = vec![0; len];
+  SystemRandom::new().fill(&mut bytes).map_err(|_| ErrorKind::Msg("could not generate random bytes".into()))?;
+  Ok(bytes)
+}
+
+/// Splits an ID into the paste ID and the decoded key from the URL fragment.
+fn split_id(id: &str) -> Result<(&str, Vec<u8>)> {
+  let (paste_id, key) = match id.find('#') {
+    Some(i) => (&id[..i], &id[i + 1..]),
+    None => bail!("the privatebin url is missing its key, so the paste cannot be decrypted")
+  };
+  let key = bs58::decode(key).into_vec().chain_err(|| "the privatebin key is not valid base58")?;
+  Ok((paste_id, key))
+}
+
+#[derive(Debug, Serialize)]
+struct PrivateBinUpload {
+  v: u8,
+  adata: Value,
+  ct: String,
+  meta: PrivateBinMeta
+}
+
+#[derive(Debug, Serialize)]
+struct PrivateBinMeta {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  expire: Option<&'static str>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PrivateBinContent {
+  #[serde(default)]
+  paste: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  attachment: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  attachment_name: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+struct PrivateBinResponse {
+  status: u8,
+  message: Option<String>,
+  id: Option<String>,
+  deletetoken: Option<String>,
+  v: Option<u8>,
+  adata: Option<Value>,
+  ct: Option<String>
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use config::{Config, CommandLineOptions};
+
+  use serde_json;
+
+  use std::sync::Arc;
+
+  // a version 2 paste containing "hello from privatebin\n", as a PrivateBin server returns it, and its key
+  const PASTE: &'static str = r#"{"status":0,"id":"2a7b0b6f3c4e5d61","url":"/?2a7b0b6f3c4e5d61","v":2,"adata":[["HsoAcJZc7wvPOMEydNebBw==","asZA2nE38Gk=",100000,256,128,"aes","gcm","zlib"],"plaintext",0,0],"ct":"g04ZXAeVizUPz+cOJoCQ3Y8FArBmL6eVgkzSR+sG93Sv1+TxvZjYvJQ9aAw6JjoFyPMjrOU=","meta":{"created":1700000000,"time_to_live":86400},"comments":[],"comment_count":0,"comment_offset":0,"@context":"?jsonld=paste"}"#;
+  const KEY: &'static str = "5mUkXLE74UcBCAFGZ86Jt7h9VT5zsFGNgdb1zkjUxAhb";
+
+  fn privatebin() -> PrivateBin {
+    PrivateBin::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+  }
+
+  fn key() -> Vec<u8> {
+    split_id(&format!("2a7b0b6f3c4e5d61#{}", KEY)).unwrap().1
+  }
+
+  #[test]
+  fn decrypts_paste() {
+    let paste: PrivateBinResponse = serde_json::from_str(PASTE).unwrap();
+    let content = privatebin().decrypt(&paste, &key()).unwrap();
+    assert_eq!(content.paste, "hello from privatebin\n");
+    assert!(content.attachment.is_none());
+  }
+
+  #[test]
+  fn rejects_wrong_key() {
+    let paste: PrivateBinResponse = serde_json::from_str(PASTE).unwrap();
+    let mut key = key();
+    key[0] ^= 1;
+    assert!(privatebin().decrypt(&paste, &key).is_err());
+  }
+
+  #[test]
+  fn rejects_too_many_iterations() {
+    let paste = PASTE.replace(",100000,", ",4294967295,");
+    let paste: PrivateBinResponse = serde_json::from_str(&paste).unwrap();
+    let err = privatebin().decrypt(&paste, &key()).unwrap_err();
+    assert!(err.to_string().contains("iterations"));
+  }
+
+  #[test]
+  fn round_trip() {
+    let bin = privatebin();
+    let file = UploadFile::new("hello.txt".to_owned(), b"hello again".to_vec());
+    let (upload, key) = bin.encrypt(&file).unwrap();
+    let paste = PrivateBinResponse {
+      status: 0,
+      message: None,
+      id: None,
+      deletetoken: None,
+      v: Some(upload.v),
+      adata: Some(upload.adata),
+      ct: Some(upload.ct)
+    };
+    assert_eq!(bin.decrypt(&paste, &key).unwrap().paste, "hello again");
+  }
+}
@@ -1,29 +1,38 @@
 use url::Url;
 use url::form_urlencoded;
-use hyper::Client;
-use serde_json;
 
-use lib::*;
-use lib::Result;
-use lib::error::*;
-use lib::files::*;
+use ::*;
+use Result;
+use error::*;
+use files::*;
+use transport::HttpClient;
+use config::Config;
 
 use std::io::Read;
+use std::sync::Arc;
+
+// sprunge has no HTTPS support
+const DEFAULT_SERVER: &'static str = "http://sprunge.us";
 
 pub struct Sprunge {
-  client: Client
+  client: HttpClient,
+  server: String,
+  host: String
 }
 
 impl Sprunge {
-  pub fn new() -> Sprunge {
+  pub fn new(config: Arc<Config>) -> Sprunge {
+    let server = super::server(&config.sprunge.server, DEFAULT_SERVER);
+    let host = super::host(&server);
     Sprunge {
-      client: Client::new()
+      client: ::transport::new_client(),
+      server: server,
+      host: host
     }
   }
 
   fn create_url(&self, id: &str) -> String {
-    // sprunge has no HTTPS support
-    format!("http://sprunge.us/{}", id)
+    format!("{}/{}", self.server, id)
   }
 
   fn id_from_url(&self, url: &str) -> Option<String> {
@@ -40,11 +49,11 @@ impl Bin for Sprunge {
   }
 
   fn html_host(&self) -> &str {
-    "sprunge.us"
+    &self.host
   }
 
   fn raw_host(&self) -> &str {
-    "sprunge.us"
+    &self.host
   }
 }
 
@@ -79,29 +88,7 @@ impl CreatesHtmlUrls for Sprunge {
 impl CreatesRawUrls for Sprunge {
   fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
     let url = self.create_url(id);
-    let mut res = self.client.get(&url).send()?;
-    let mut content = String::new();
-    res.read_to_string(&mut content)?;
-    if res.status.class().default_code() != ::hyper::Ok {
-      debug!("bad status code");
-      return Err(ErrorKind::InvalidStatus(res.status_raw().0, Some(content)).into());
-    }
-    let parsed: serde_json::Result<Vec<IndexedFile>> = serde_json::from_str(&content);
-    match parsed {
-      Ok(is) => {
-        debug!("file was an index, so checking its urls");
-        let ids: Option<Vec<(String, String)>> = is.iter().map(|x| self.id_from_html_url(&x.url).map(|i| (x.name.clone(), i))).collect();
-        let ids = match ids {
-          Some(i) => i,
-          None => {
-            debug!("could not parse an ID from one of the URLs in the index");
-            bail!("one of the URLs in the index did not contain a valid ID");
-          }
-        };
-        Ok(ids.into_iter().map(|(name, id)| PasteUrl::raw(Some(PasteFileName::Explicit(name)), self.create_url(&id))).collect())
-      },
-      Err(_) => Ok(vec![PasteUrl::Downloaded(url, DownloadedFile::new(PasteFileName::Guessed(id.to_owned()), content))])
-    }
+    super::download_or_index(self, id, &url, &url, |id| self.create_url(id))
   }
 
   fn id_from_raw_url(&self, url: &str) -> Option<String> {
@@ -118,9 +105,9 @@ impl HasFeatures for Sprunge {
 impl UploadsSingleFiles for Sprunge {
   fn upload_single(&self, contents: &UploadFile) -> Result<PasteUrl> {
     debug!("uploading single file");
-    let mut res = self.client.post("http://sprunge.us")
+    let mut res = self.client.post(&self.server)
       .body(&form_urlencoded::Serializer::new(String::new())
-        .append_pair("sprunge", &contents.content)
+        .append_pair("sprunge", contents.text()?)
         .finish())
       .send()?;
     debug!("response: {:?}", res);
@@ -136,8 +123,16 @@ impl UploadsSingleFiles for Sprunge {
   }
 }
 
+impl Updates for Sprunge {}
+
+impl Deletes for Sprunge {}
+
 impl HasClient for Sprunge {
-  fn client(&self) -> &Client {
+  fn client(&self) -> &HttpClient {
     &self.client
   }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
 }
@@ -1,4 +1,4 @@
-use clap::{App, Arg, AppSettings};
+use clap::{App, Arg, AppSettings, SubCommand};
 
 type StaticApp = App<'static, 'static>;
 
@@ -10,7 +10,7 @@ fn base_app() -> StaticApp {
     .help_message("print help information and exit")
     .setting(AppSettings::DisableVersion)
     .arg(Arg::with_name("inputs")
-      .help("inputs to the program, either files or URLs")
+      .help("inputs to the program, either files, URLs or references to previous uploads (@last, @2 or @alias)")
       .takes_value(true)
       .value_name("input")
       .multiple(true))
@@ -21,10 +21,9 @@ fn base_app() -> StaticApp {
     .arg(Arg::with_name("bin")
       .long("bin")
       .short("b")
-      .help("specify the upload bin")
+      .help("specify the upload bin (see --list-bins)")
       .takes_value(true)
-      .value_name("bin")
-      .possible_values(&["hastebin", "sprunge", "gist", "fedora", "bitbucket", "pastebin", "pastegg"]))
+      .value_name("bin"))
     .arg(Arg::with_name("public")
       .long("public")
       .short("P")
@@ -107,6 +106,90 @@ fn base_app() -> StaticApp {
       .help("writes one file to the given path or writes multiple files in the given path as a directory")
       .takes_value(true)
       .value_name("path"))
+    .arg(Arg::with_name("update")
+      .long("update")
+      .short("U")
+      .help("replace or add files in an existing paste (a URL or a reference like @last) instead of creating a new one")
+      .takes_value(true)
+      .value_name("url")
+      .conflicts_with_all(&["list-bins", "range", "list-all", "output"]))
+    .arg(Arg::with_name("expires")
+      .long("expires")
+      .short("e")
+      .help("set the paste to expire after the given duration (e.g. \"10m\", \"1h\", \"2d\", \"1w\")")
+      .takes_value(true)
+      .value_name("duration"))
+    .arg(Arg::with_name("burn")
+      .long("burn")
+      .short("B")
+      .help("set the paste to be deleted after it is read once"))
+    .arg(Arg::with_name("password")
+      .long("password")
+      .help("protect the paste with a password, or give the password to download a protected paste")
+      .takes_value(true)
+      .value_name("password"))
+    .arg(Arg::with_name("discussion")
+      .long("discussion")
+      .help("allow comments on the paste"))
+    .arg(Arg::with_name("title")
+      .long("title")
+      .short("t")
+      .help("set the title of the paste")
+      .takes_value(true)
+      .value_name("title"))
+    .arg(Arg::with_name("description")
+      .long("description")
+      .short("D")
+      .help("set the description of the paste")
+      .takes_value(true)
+      .value_name("description"))
+    .arg(Arg::with_name("lang")
+      .long("lang")
+      .help("set the language used to highlight the uploaded files, instead of guessing it from their names (\"file.rs:lang=rust\" sets it for one file)")
+      .takes_value(true)
+      .value_name("language"))
+    .arg(Arg::with_name("redact")
+      .long("redact")
+      .short("R")
+      .help("replace secrets, email addresses, IP addresses, home directories and hostnames before uploading"))
+    .arg(Arg::with_name("encrypt")
+      .long("encrypt")
+      .short("E")
+      .help("encrypt files before uploading, putting the key in the fragment of the returned URLs. file names are not encrypted"))
+    .arg(Arg::with_name("alias")
+      .long("alias")
+      .help("name the upload so it can be referred to later as @alias")
+      .takes_value(true)
+      .value_name("alias"))
+    .subcommand(SubCommand::with_name("delete")
+      .about("delete a paste")
+      .arg(Arg::with_name("url")
+        .help("the URL of the paste to delete, or a reference to a previous upload like @last")
+        .takes_value(true)
+        .value_name("url")
+        .required(true))
+      .arg(Arg::with_name("key")
+        .long("key")
+        .short("k")
+        .help("the deletion key returned when the paste was created, if the bin uses one")
+        .takes_value(true)
+        .value_name("key")))
+    .subcommand(SubCommand::with_name("history")
+      .about("list or search previous uploads")
+      .arg(Arg::with_name("query")
+        .help("only show uploads whose bin, URLs or file names contain this")
+        .takes_value(true)
+        .value_name("query"))
+      .arg(Arg::with_name("limit")
+        .long("limit")
+        .short("n")
+        .help("show at most this many uploads")
+        .takes_value(true)
+        .value_name("number"))
+      .arg(Arg::with_name("json")
+        .long("json")
+        .short("j")
+        .help("output JSON information")))
 }
 
 pub fn add_feature_options(app: StaticApp) -> StaticApp {
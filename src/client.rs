@@ -0,0 +1,491 @@
+//! Uploading and downloading pastes with the same bins, configuration and safety checks as the bins command.
+//!
+//! The quickest way to upload is the `upload` builder:
+//!
+//! ```ignore
+//! let urls = bins::upload(vec![UploadFile::new("hello.txt".to_owned(), b"hello".to_vec())])
+//!   .to("gist")
+//!   .private(true)
+//!   .send()?;
+//! ```
+//!
+//! A `Client` can be kept around instead, to use the same bins for more than one upload or download.
+
+use error::*;
+use files::{Paste, UploadFile};
+use bins;
+use config::{Config, CommandLineOptions};
+use encrypt::Key;
+use redact::Redactor;
+use scan::Scanner;
+use units::{parse_duration, parse_size};
+use {Bin, BinFeature, DownloadInfo, PasteUrl};
+
+use glob::Pattern;
+use url::Url;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Every bin, along with the configuration and options they were made with.
+pub struct Client {
+  bins: BTreeMap<String, Box<Bin>>,
+  /// The names of the bins that are plugins, which are only asked about URLs that no other bin uses.
+  plugins: BTreeSet<String>,
+  config: Arc<Config>,
+  options: Arc<CommandLineOptions>
+}
+
+impl Client {
+  /// Creates a client with every bin: the built-in bins, the bins defined in the config file and plugins.
+  pub fn new(config: Arc<Config>, options: Arc<CommandLineOptions>) -> Result<Client> {
+    let mut bins = bins::configured(config.clone(), options.clone());
+    let mut plugins = BTreeSet::new();
+    for plugin in bins::plugins(config.clone(), options.clone(), &bins) {
+      plugins.insert(plugin.name().to_owned());
+      bins.insert(plugin.name().to_owned(), Box::new(plugin));
+    }
+    Ok(Client {
+      bins: bins,
+      plugins: plugins,
+      config: config,
+      options: options
+    })
+  }
+
+  pub fn bins(&self) -> &BTreeMap<String, Box<Bin>> {
+    &self.bins
+  }
+
+  pub fn config(&self) -> &Config {
+    &self.config
+  }
+
+  pub fn options(&self) -> &CommandLineOptions {
+    &self.options
+  }
+
+  pub fn bin(&self, name: &str) -> Result<&Box<Bin>> {
+    self.bins.get(name).ok_or_else(|| format!("there is no bin called \"{}\"", name).into())
+  }
+
+  /// Uploads files to the bin called `name`, after checking them like the bins command does. Files are encrypted if
+  /// the options ask for it, and the key is added to the returned URLs.
+  pub fn upload(&self, name: &str, files: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    let bin = self.bin(name)?;
+    self.check_features(bin.as_ref())?;
+    for file in files {
+      self.check_size(&file.name, file.content.len() as u64)?;
+    }
+    self.check_file_patterns(&files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>())?;
+    let mut files = files.to_vec();
+    if let Some(true) = self.options.redact {
+      self.redact(&mut files)?;
+    }
+    #[cfg(feature = "file_type_checking")]
+    self.check_file_types(&files)?;
+    self.check_secrets(&files)?;
+    let key = self.new_key(bin.as_ref())?;
+    let encrypted = self.encrypt(key.as_ref(), &files)?;
+    let sent_files = encrypted.as_ref().unwrap_or(&files);
+    self.check_binary(bin.as_ref(), sent_files)?;
+    let urls = bin.upload(sent_files, true)?;
+    Ok(match key {
+      Some(ref k) => urls.into_iter().map(|u| k.add_to(u)).collect(),
+      None => urls
+    })
+  }
+
+  /// Downloads the paste at a URL, decrypting it if the URL has a key.
+  pub fn download(&self, url: &str, info: &DownloadInfo) -> Result<Paste> {
+    let (bin, id, key) = self.locate(url)?;
+    let paste = bin.download(&id, info)?;
+    Ok(match key {
+      Some(ref k) => match paste {
+        Paste::Single(f) => Paste::Single(k.decrypt(f)?),
+        Paste::Multiple(fs) => Paste::Multiple(fs.into_iter().map(|f| k.decrypt(f)).collect::<Result<_>>()?)
+      },
+      None => paste
+    })
+  }
+
+  /// Deletes the paste at a URL.
+  pub fn delete(&self, url: &str) -> Result<()> {
+    let (bin, id, _) = self.locate(url)?;
+    if !bin.features().contains(&BinFeature::Deletable) {
+      bail!("{} does not support deleting pastes", bin.name());
+    }
+    bin.delete(&id)
+  }
+
+  /// Finds the bin and ID for a URL, along with the encryption key in the URL's fragment, if any.
+  pub fn locate(&self, input: &str) -> Result<(&Box<Bin>, String, Option<Key>)> {
+    let mut url = Url::parse(input).chain_err(|| format!("{} is not a valid url", input))?;
+    let key = Key::from_url(input)?;
+    if key.is_some() {
+      url.set_fragment(None);
+    }
+    let (bin, id) = self.bin_for_url(&url)?;
+    Ok((bin, id, key))
+  }
+
+  pub fn bin_for_url(&self, url: &Url) -> Result<(&Box<Bin>, String)> {
+    // file urls have no host, so they go to bins that write to disk, which have an empty host
+    let host = match bins::url_host(url) {
+      Some(h) => h,
+      None if url.scheme() == "file" => String::new(),
+      None => bail!("url was missing a host")
+    };
+    // plugins have to be run to find their hosts, so they are only asked if no other bin uses the host
+    let found = match self.find_bin(&host, false)? {
+      Some(f) => f,
+      None => self.find_bin(&host, true)?.ok_or_else(|| ErrorKind::Msg(format!("no bin uses the host {}", host)))?
+    };
+    let (bin, is_html_url) = found;
+    let id = if is_html_url {
+      bin.id_from_html_url(url.as_str())
+    } else {
+      bin.id_from_raw_url(url.as_str())
+    };
+    let id = id.ok_or_else(|| ErrorKind::Msg("could not parse ID from URL".into()))?;
+    Ok((bin, id))
+  }
+
+  /// Finds the bin with `host` as its raw host or, failing that, its html host, along with whether it was the html host.
+  /// Only plugins are searched if `plugins` is true, and only other bins if it is not.
+  fn find_bin(&self, host: &str, plugins: bool) -> Result<Option<(&Box<Bin>, bool)>> {
+    let candidates: Vec<(&String, &Box<Bin>)> = self.bins.iter()
+      .filter(|&(name, _)| self.plugins.contains(name) == plugins)
+      .collect();
+    for &is_html_url in &[false, true] {
+      let matches: Vec<(&String, &Box<Bin>)> = candidates.iter()
+        .filter(|&&(_, b)| if is_html_url { b.html_host() == host } else { b.raw_host() == host })
+        .cloned()
+        .collect();
+      match matches.len() {
+        0 => {},
+        1 => return Ok(Some((matches[0].1, is_html_url))),
+        _ => {
+          let names: Vec<&str> = matches.iter().map(|&(n, _)| n.as_str()).collect();
+          bail!("more than one bin uses the host {}: {}", host, names.join(", "));
+        }
+      }
+    }
+    Ok(None)
+  }
+
+  pub fn file_size_limit(&self) -> Result<Option<u64>> {
+    let s = match self.config.general.file_size_limit {
+      Some(ref x) => x,
+      None => return Ok(None)
+    };
+    parse_size(s).map(Some).chain_err(|| "the file size limit specified in the config is invalid")
+  }
+
+  fn requested_features(&self) -> HashMap<BinFeature, Option<bool>> {
+    let mut map = HashMap::new();
+    map.insert(BinFeature::Private, self.options.private);
+    map.insert(BinFeature::Public, self.options.private.map(|x| !x));
+    map.insert(BinFeature::Authed, self.options.authed);
+    map.insert(BinFeature::Anonymous, self.options.authed.map(|x| !x));
+    map.insert(BinFeature::SingleNaming, self.options.name.as_ref().map(|_| true));
+    map.insert(BinFeature::Expiring, self.options.expires.map(|_| true));
+    map.insert(BinFeature::BurnAfterReading, self.options.burn);
+    map.insert(BinFeature::Titled, self.options.title.as_ref().map(|_| true));
+    map.insert(BinFeature::Described, self.options.description.as_ref().map(|_| true));
+    map.insert(BinFeature::PasswordProtected, self.options.password.as_ref().map(|_| true));
+    map.insert(BinFeature::Discussion, self.options.discussion);
+    map
+  }
+
+  pub fn check_features(&self, bin: &Bin) -> Result<()> {
+    let bin_features = bin.features();
+    let features = self.requested_features();
+    for (feature, status) in features {
+      if let Some(true) = status {
+        if !bin_features.contains(&feature) {
+          if let Some(true) = self.config.safety.warn_on_unsupported {
+            warn!("{} does not support {} pastes", bin.name(), feature);
+          }
+          if let Some(true) = self.config.safety.cancel_on_unsupported {
+            return match self.options.force {
+              Some(true) => {
+                warn!("forcing upload with unsupported features");
+                Ok(())
+              },
+              _ => bail!("bins stopped because {} does not support {} pastes", bin.name(), feature)
+            }
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks a file's size against the file size limit in the config file.
+  pub fn check_size(&self, name: &str, size: u64) -> Result<()> {
+    let limit = match self.file_size_limit()? {
+      Some(l) => l,
+      None => return Ok(())
+    };
+    if size <= limit {
+      return Ok(());
+    }
+    if let Some(true) = self.options.force {
+      warn!("{} is {} bytes, which is over the {} byte limit", name, size, limit);
+      return Ok(());
+    }
+    bail!("{} is {} byte{}, which is over the size limit of {} byte{}",
+      name,
+      size,
+      if size == 1 { "" } else { "s" },
+      limit,
+      if limit == 1 { "" } else { "s" });
+  }
+
+  /// Checks files against the disallowed file patterns in the config file. Each path is matched by its file name, as
+  /// given and, if it exists, as an absolute path, so the names of files that were not read from disk work too.
+  pub fn check_file_patterns(&self, paths: &[&str]) -> Result<()> {
+    let patterns = match self.config.safety.disallowed_file_patterns {
+      Some(ref p) if !p.is_empty() => p,
+      _ => return Ok(())
+    };
+    let patterns: Vec<Pattern> = patterns.iter()
+      .map(|p| Pattern::new(p).chain_err(|| format!("the disallowed file pattern \"{}\" is invalid", p)))
+      .collect::<Result<_>>()?;
+
+    let mut disallowed = Vec::new();
+    for &path_str in paths {
+      let path = Path::new(path_str);
+      let name = path.file_name().and_then(|n| n.to_str()).unwrap_or(path_str);
+      let full_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+      let matched = patterns.iter().find(|p| p.matches(name) || p.matches_path(path) || p.matches_path(&full_path));
+      if let Some(pattern) = matched {
+        disallowed.push(format!("{} (matches {})", path_str, pattern));
+      }
+    }
+
+    if disallowed.is_empty() {
+      return Ok(());
+    }
+    if let Some(true) = self.options.force {
+      for file in &disallowed {
+        warn!("forcing upload of disallowed file {}", file);
+      }
+      return Ok(());
+    }
+    Err(ErrorKind::DisallowedFiles(disallowed).into())
+  }
+
+  pub fn check_binary(&self, bin: &Bin, files: &[UploadFile]) -> Result<()> {
+    if bin.features().contains(&BinFeature::BinaryFiles) {
+      return Ok(());
+    }
+    match files.iter().find(|f| f.is_binary()) {
+      Some(f) => bail!("bins stopped because {} does not support {} pastes ({} is not valid utf-8)",
+        bin.name(),
+        BinFeature::BinaryFiles,
+        f.name),
+      None => Ok(())
+    }
+  }
+
+  pub fn redact(&self, files: &mut [UploadFile]) -> Result<()> {
+    let custom_patterns = self.config.safety.redact_patterns.clone().unwrap_or_default();
+    let redactor = Redactor::new(&custom_patterns)?;
+    for file in files {
+      let counts = redactor.redact(file);
+      if counts.is_empty() {
+        info!("nothing was redacted from {}", file.name);
+        continue;
+      }
+      let summary: Vec<String> = counts.into_iter()
+        .map(|(kind, count)| format!("{} ({})", kind, count))
+        .collect();
+      info!("redacted {} from {}", summary.join(", "), file.name);
+    }
+    Ok(())
+  }
+
+  pub fn check_secrets(&self, files: &[UploadFile]) -> Result<()> {
+    if let Some(false) = self.config.safety.scan_for_secrets {
+      return Ok(());
+    }
+    let custom_patterns = self.config.safety.secret_patterns.clone().unwrap_or_default();
+    let scanner = Scanner::new(&custom_patterns, self.config.safety.scan_entropy.unwrap_or(false))?;
+    let findings: Vec<String> = files.iter()
+      .flat_map(|f| scanner.scan(f))
+      .map(|f| f.to_string())
+      .collect();
+    if findings.is_empty() {
+      return Ok(());
+    }
+    match self.options.force {
+      Some(true) => {
+        for finding in &findings {
+          warn!("forcing upload with possible secret: {}", finding);
+        }
+        Ok(())
+      },
+      _ => Err(ErrorKind::SecretsFound(findings).into())
+    }
+  }
+
+  #[cfg(feature = "file_type_checking")]
+  pub fn check_file_types(&self, files: &[UploadFile]) -> Result<()> {
+    use magic::{Cookie, flags};
+
+    let cookie = Cookie::open(flags::NONE)?;
+    cookie.load(&[""; 0])?;
+    for upload_file in files {
+      let kind = cookie.buffer(&upload_file.content)?;
+      if let Some(ref disallowed) = self.config.safety.disallowed_file_types {
+        if disallowed.contains(&kind) {
+          return match self.options.force {
+            Some(true) => {
+              warn!("forcing upload with disallowed file type: ({} is {}, which is disallowed)", upload_file.name, kind);
+              Ok(())
+            },
+            _ => Err(ErrorKind::InvalidFileType(upload_file.name.clone(), kind).into())
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Generates a key if the options ask for encryption and the bin does not already encrypt pastes itself.
+  pub fn new_key(&self, bin: &Bin) -> Result<Option<Key>> {
+    if self.options.encrypt != Some(true) {
+      return Ok(None);
+    }
+    if bin.features().contains(&BinFeature::Encrypted) {
+      info!("{} already encrypts pastes, so --encrypt was ignored", bin.name());
+      return Ok(None);
+    }
+    Key::generate().map(Some)
+  }
+
+  /// Encrypts the files if there is a key, returning `None` if there is not.
+  pub fn encrypt(&self, key: Option<&Key>, files: &[UploadFile]) -> Result<Option<Vec<UploadFile>>> {
+    let key = match key {
+      Some(k) => k,
+      None => return Ok(None)
+    };
+    let encrypted = files.iter().map(|f| key.encrypt(f)).collect::<Result<Vec<_>>>()?;
+    debug!("encrypted {} file(s)", encrypted.len());
+    Ok(Some(encrypted))
+  }
+}
+
+/// Starts an upload of `files`, which is sent with `Upload::send`.
+pub fn upload(files: Vec<UploadFile>) -> Upload {
+  Upload {
+    files: files,
+    bin: None,
+    config: None,
+    options: CommandLineOptions::default()
+  }
+}
+
+/// An upload being built by `upload`. Settings that are not set come from the config file, just like with the bins
+/// command.
+pub struct Upload {
+  files: Vec<UploadFile>,
+  bin: Option<String>,
+  config: Option<Config>,
+  options: CommandLineOptions
+}
+
+impl Upload {
+  /// The bin to upload to. The default bin in the config file is used if this is not set.
+  pub fn to(mut self, bin: &str) -> Upload {
+    self.bin = Some(bin.to_owned());
+    self
+  }
+
+  /// Uses this configuration instead of loading the config file.
+  pub fn config(mut self, config: Config) -> Upload {
+    self.config = Some(config);
+    self
+  }
+
+  pub fn private(mut self, private: bool) -> Upload {
+    self.options.private = Some(private);
+    self
+  }
+
+  pub fn authed(mut self, authed: bool) -> Upload {
+    self.options.authed = Some(authed);
+    self
+  }
+
+  pub fn title(mut self, title: &str) -> Upload {
+    self.options.title = Some(title.to_owned());
+    self
+  }
+
+  pub fn description(mut self, description: &str) -> Upload {
+    self.options.description = Some(description.to_owned());
+    self
+  }
+
+  /// Makes the paste expire after this many seconds.
+  pub fn expires(mut self, seconds: u64) -> Upload {
+    self.options.expires = Some(seconds);
+    self
+  }
+
+  pub fn burn(mut self, burn: bool) -> Upload {
+    self.options.burn = Some(burn);
+    self
+  }
+
+  pub fn password(mut self, password: &str) -> Upload {
+    self.options.password = Some(password.to_owned());
+    self
+  }
+
+  pub fn encrypt(mut self, encrypt: bool) -> Upload {
+    self.options.encrypt = Some(encrypt);
+    self
+  }
+
+  pub fn redact(mut self, redact: bool) -> Upload {
+    self.options.redact = Some(redact);
+    self
+  }
+
+  /// Uploads even if a safety check fails, like `--force`.
+  pub fn force(mut self, force: bool) -> Upload {
+    self.options.force = Some(force);
+    self
+  }
+
+  pub fn send(self) -> Result<Vec<PasteUrl>> {
+    let config = match self.config {
+      Some(c) => c,
+      None => Config::load()?
+    };
+    let bin = self.bin
+      .or_else(|| config.defaults.bin.clone())
+      .and_then(|x| if x.trim().is_empty() { None } else { Some(x) })
+      .ok_or_else(|| ErrorKind::Msg("no bin was specified".into()))?;
+    // bins fall back to the default visibility themselves, but these defaults are applied by the command line
+    let mut options = self.options;
+    if options.expires.is_none() {
+      if let Some(ref expires) = config.defaults.expires {
+        options.expires = Some(parse_duration(expires).chain_err(|| "the default expiry in the config is invalid")?);
+      }
+    }
+    if options.title.is_none() {
+      options.title = config.defaults.title.clone();
+    }
+    if options.description.is_none() {
+      options.description = config.defaults.description.clone();
+    }
+    let client = Client::new(Arc::new(config), Arc::new(options))?;
+    client.upload(&bin, &self.files)
+  }
+}
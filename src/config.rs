@@ -1,24 +1,210 @@
 use range::BidirectionalRange;
+use error::*;
+
+use flate2::read::GzDecoder;
+use toml::Value;
+use toml::value::Table;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
 pub const DEFAULT_CONFIG_GZIP: &'static [u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/bins.cfg.gz"));
 
+/// The sections for built-in bins, whose endpoints can be changed with environment variables.
+const ENDPOINT_SECTIONS: &'static [&'static str] = &[
+  "sprunge",
+  "hastebin",
+  "gist",
+  "pastebin",
+  "pastegg",
+  "fedora",
+  "bitbucket",
+  "privatebin",
+  "gitlab",
+  "fiche",
+  "0x0",
+  "transfersh",
+  "local"
+];
+
+/// The settings that say where a bin sends its requests.
+const ENDPOINT_KEYS: &'static [&'static str] = &["server", "api_server", "raw_server", "url", "host", "port", "directory"];
+
+/// Overrides endpoint settings in a parsed config file with environment variables named `BINS_<SECTION>_<SETTING>`, so
+/// `BINS_GIST_API_SERVER` sets `api_server` under `[gist]`. Bins defined under `[bins]` use their name as the section,
+/// so `BINS_WORK_HASTE_SERVER` sets `server` under `[bins.work-haste]`.
+pub fn override_endpoints_from_env(config: &mut Value) -> Result<()> {
+  let table = match *config {
+    Value::Table(ref mut t) => t,
+    _ => return Ok(())
+  };
+  for section in ENDPOINT_SECTIONS {
+    override_section(table, section)?;
+  }
+  if let Some(&mut Value::Table(ref mut bins)) = table.get_mut("bins") {
+    let names: Vec<String> = bins.keys().cloned().collect();
+    for name in names {
+      override_section(bins, &name)?;
+    }
+  }
+  Ok(())
+}
+
+fn override_section(table: &mut Table, section: &str) -> Result<()> {
+  for key in ENDPOINT_KEYS {
+    let var = format!("BINS_{}_{}", env_name(section), env_name(key));
+    let value = match env::var(&var) {
+      Ok(v) => v,
+      Err(_) => continue
+    };
+    let value = if *key == "port" {
+      Value::Integer(value.parse().chain_err(|| format!("{} is not a valid port", var))?)
+    } else {
+      Value::String(value)
+    };
+    let entry = table.entry(section.to_owned()).or_insert_with(|| Value::Table(Table::new()));
+    if let Value::Table(ref mut settings) = *entry {
+      settings.insert(key.to_string(), value);
+    }
+  }
+  Ok(())
+}
+
+fn env_name(name: &str) -> String {
+  name.chars().map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' }).collect()
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct Config {
   pub general: ConfigGeneral,
   pub safety: ConfigSafety,
   pub defaults: ConfigDefaults,
+  pub sprunge: ConfigSprunge,
+  pub fedora: ConfigFedora,
   pub gist: ConfigGist,
   pub pastebin: ConfigPastebin,
   pub hastebin: ConfigHastebin,
   pub bitbucket: ConfigBitbucket,
   pub pastegg: ConfigPasteGg,
+  pub privatebin: ConfigPrivateBin,
+  pub gitlab: ConfigGitLab,
+  pub fiche: ConfigFiche,
+  #[serde(rename = "0x0")]
+  pub null_pointer: ConfigNullPointer,
+  #[serde(rename = "transfersh")]
+  pub transfer_sh: ConfigTransferSh,
+  pub local: ConfigLocal,
+  pub bins: BTreeMap<String, ConfigBin>,
+}
+
+impl Config {
+  /// Loads the configuration file, creating it with the default configuration if there is none.
+  pub fn load() -> Result<Config> {
+    let mut f = match find_config_path() {
+      Some(p) => File::open(p)?,
+      None => create_config_file()?
+    };
+    let mut content = String::new();
+    f.read_to_string(&mut content)?;
+    Config::parse(&content)
+  }
+
+  /// Parses a configuration file, applying endpoints set in the environment.
+  pub fn parse(content: &str) -> Result<Config> {
+    let mut value: Value = ::toml::from_str(content).chain_err(|| "could not parse configuration file")?;
+    override_endpoints_from_env(&mut value).chain_err(|| "could not apply endpoints from the environment")?;
+    value.try_into().chain_err(|| "could not parse configuration file")
+  }
+}
+
+/// The path of the configuration file, if there is one.
+pub fn find_config_path() -> Option<PathBuf> {
+  if let Ok(xdg_dir) = env::var("XDG_CONFIG_DIR") {
+    let xdg_config_path = Path::new(&xdg_dir).join("bins.cfg");
+    if xdg_config_path.exists() {
+      return Some(xdg_config_path.to_owned());
+    }
+  }
+  if let Ok(home_dir) = env::var("HOME") {
+    let home_config_folder = Path::new(&home_dir).join(".config").join("bins.cfg");
+    if home_config_folder.exists() {
+      return Some(home_config_folder.to_owned());
+    }
+    let home_config = Path::new(&home_dir).join(".bins.cfg");
+    if home_config.exists() {
+      return Some(home_config.to_owned());
+    }
+  }
+  None
+}
+
+fn create_xdg_config_file() -> Result<File> {
+  if let Ok(xdg_dir) = env::var("XDG_CONFIG_DIR") {
+    let xdg_path = Path::new(&xdg_dir);
+    let xdg_config_path = xdg_path.join("bins.cfg");
+    if xdg_path.exists() && xdg_path.is_dir() && !xdg_config_path.exists() {
+      return OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(xdg_config_path)
+        .chain_err(|| "could not open/create XDG_CONFIG_DIR config file");
+    }
+  }
+  Err(ErrorKind::Config.into())
+}
+
+fn create_home_config_file() -> Result<File> {
+  if let Ok(home_dir) = env::var("HOME") {
+    let home = Path::new(&home_dir);
+    let home_folder = home.join(".config");
+    let home_folder_config = home_folder.join("bins.cfg");
+    if home_folder.exists() && home_folder.is_dir() && !home_folder_config.exists() {
+      return OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(home_folder_config)
+        .chain_err(|| "could not open/create HOME/.config config file");
+    }
+    let home_config = Path::new(&home_dir).join(".bins.cfg");
+    if home.exists() && home.is_dir() && !home_config.exists() {
+      return OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(home_config)
+        .chain_err(|| "could not open/create HOME config file");
+    }
+  }
+  Err(ErrorKind::Config.into())
+}
+
+fn create_config_file() -> Result<File> {
+  let mut f = match create_xdg_config_file() {
+    Ok(f) => f,
+    Err(_) => match create_home_config_file() {
+      Ok(hf) => hf,
+      Err(_) => return Err(ErrorKind::Config.into())
+    }
+  };
+  let mut default_config = String::new();
+  GzDecoder::new(DEFAULT_CONFIG_GZIP)?
+    .read_to_string(&mut default_config)?;
+  f.write_all(default_config.as_bytes())?;
+  f.seek(SeekFrom::Start(0))?;
+  Ok(f)
 }
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct ConfigGeneral {
-  pub file_size_limit: Option<String>
+  pub file_size_limit: Option<String>,
+  pub history: Option<bool>
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -27,7 +213,11 @@ pub struct ConfigSafety {
   pub disallowed_file_patterns: Option<Vec<String>>,
   pub disallowed_file_types: Option<Vec<String>>,
   pub cancel_on_unsupported: Option<bool>,
-  pub warn_on_unsupported: Option<bool>
+  pub warn_on_unsupported: Option<bool>,
+  pub scan_for_secrets: Option<bool>,
+  pub scan_entropy: Option<bool>,
+  pub secret_patterns: Option<Vec<String>>,
+  pub redact_patterns: Option<Vec<String>>
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -36,23 +226,44 @@ pub struct ConfigDefaults {
   pub private: Option<bool>,
   pub authed: Option<bool>,
   pub bin: Option<String>,
-  pub copy: Option<bool>
+  pub copy: Option<bool>,
+  pub expires: Option<String>,
+  pub title: Option<String>,
+  pub description: Option<String>
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigSprunge {
+  pub server: Option<String>
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFedora {
+  pub server: Option<String>
 }
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct ConfigGist {
   pub username: Option<String>,
-  pub access_token: Option<String>
+  pub access_token: Option<String>,
+  pub server: Option<String>,
+  pub api_server: Option<String>,
+  pub raw_server: Option<String>
 }
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(default)]
 pub struct ConfigPastebin {
-  pub api_key: Option<String>
+  pub api_key: Option<String>,
+  pub username: Option<String>,
+  pub password: Option<String>,
+  pub server: Option<String>
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Clone, Deserialize)]
 #[serde(default)]
 pub struct ConfigHastebin {
   pub server: Option<String>
@@ -62,15 +273,106 @@ pub struct ConfigHastebin {
 #[serde(default)]
 pub struct ConfigBitbucket {
   pub username: Option<String>,
-  pub app_password: Option<String>
+  pub app_password: Option<String>,
+  pub server: Option<String>,
+  pub api_server: Option<String>
 }
 
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Default, Clone, Deserialize)]
 #[serde(default)]
 pub struct ConfigPasteGg {
   pub key: Option<String>,
+  pub server: Option<String>,
+  pub api_server: Option<String>
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigPrivateBin {
+  pub server: Option<String>
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigGitLab {
+  pub url: Option<String>,
+  pub token: Option<String>,
+  pub private_visibility: Option<String>
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigFiche {
+  pub host: Option<String>,
+  pub port: Option<u16>,
+  pub url: Option<String>
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigNullPointer {
+  pub server: Option<String>
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigTransferSh {
+  pub server: Option<String>
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ConfigLocal {
+  pub directory: Option<String>,
+  pub url: Option<String>
+}
+
+/// A bin defined in the configuration file.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct ConfigBin {
+  #[serde(rename = "type")]
+  pub kind: Option<String>,
+  pub upload_url: Option<String>,
+  pub method: Option<String>,
+  pub body: Option<String>,
+  pub file_field: Option<String>,
+  pub fields: BTreeMap<String, String>,
+  pub template: Option<String>,
+  pub headers: BTreeMap<String, String>,
+  pub url_path: Option<String>,
+  pub url_regex: Option<String>,
+  pub html_url: Option<String>,
+  pub raw_url: Option<String>,
+  pub binary: Option<bool>,
+  pub server: Option<String>,
+  pub api_server: Option<String>,
+  pub key: Option<String>
+}
+
+impl ConfigBin {
+  pub fn hastebin(&self) -> ConfigHastebin {
+    ConfigHastebin {
+      server: self.server.clone()
+    }
+  }
+
+  pub fn pastegg(&self) -> ConfigPasteGg {
+    ConfigPasteGg {
+      key: self.key.clone(),
+      server: self.server.clone(),
+      api_server: self.api_server.clone()
+    }
+  }
+
+  pub fn privatebin(&self) -> ConfigPrivateBin {
+    ConfigPrivateBin {
+      server: self.server.clone()
+    }
+  }
 }
 
+/// Options for a single run of bins, which are usually given on the command line.
 #[derive(Debug, Default)]
 pub struct CommandLineOptions {
   pub authed: Option<bool>,
@@ -84,7 +386,18 @@ pub struct CommandLineOptions {
   pub copy: Option<bool>,
   pub list_all: Option<bool>,
   pub range: Option<Vec<BidirectionalRange<usize>>>,
-  pub output: Option<String>
+  pub output: Option<String>,
+  pub deletion_key: Option<String>,
+  pub expires: Option<u64>,
+  pub burn: Option<bool>,
+  pub title: Option<String>,
+  pub description: Option<String>,
+  pub language: Option<String>,
+  pub redact: Option<bool>,
+  pub alias: Option<String>,
+  pub encrypt: Option<bool>,
+  pub password: Option<String>,
+  pub discussion: Option<bool>
 }
 
 impl CommandLineOptions {
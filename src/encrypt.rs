@@ -0,0 +1,109 @@
+use base64;
+use ring::aead::{self, AES_256_GCM, OpeningKey, SealingKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use url::Url;
+
+use {PasteUrl, Result};
+use error::*;
+use files::{DownloadedFile, UploadFile};
+
+/// The first line of every encrypted file, followed by the base64-encoded nonce and ciphertext.
+const HEADER: &'static str = "bins-encrypted-v1\n";
+/// The prefix of the URL fragment that carries the key.
+const FRAGMENT_PREFIX: &'static str = "bins-key=";
+
+/// A key for AES-256-GCM. It is only ever kept in URL fragments, which browsers and bins never send to the server.
+///
+/// Only file contents are encrypted. File names, and the index listing the files of a multi-file paste, are uploaded in
+/// plain text.
+pub struct Key {
+  bytes: Vec<u8>
+}
+
+impl Key {
+  pub fn generate() -> Result<Key> {
+    let mut bytes = vec![0; AES_256_GCM.key_len()];
+    SystemRandom::new().fill(&mut bytes).map_err(|_| ErrorKind::Msg("could not generate an encryption key".into()))?;
+    Ok(Key {
+      bytes: bytes
+    })
+  }
+
+  /// Finds the key in the fragment of a URL, if there is one.
+  pub fn from_url(url: &str) -> Result<Option<Key>> {
+    let url = match Url::parse(url) {
+      Ok(u) => u,
+      Err(_) => return Ok(None)
+    };
+    let fragment = match url.fragment() {
+      Some(f) if f.starts_with(FRAGMENT_PREFIX) => &f[FRAGMENT_PREFIX.len()..],
+      _ => return Ok(None)
+    };
+    let bytes = base64::decode_config(fragment, base64::URL_SAFE).chain_err(|| "the encryption key in the url is not valid base64")?;
+    if bytes.len() != AES_256_GCM.key_len() {
+      bail!("the encryption key in the url is the wrong length");
+    }
+    Ok(Some(Key {
+      bytes: bytes
+    }))
+  }
+
+  pub fn fragment(&self) -> String {
+    format!("{}{}", FRAGMENT_PREFIX, base64::encode_config(&self.bytes, base64::URL_SAFE))
+  }
+
+  /// Adds the key to a URL returned by a bin.
+  pub fn add_to(&self, url: PasteUrl) -> PasteUrl {
+    match url {
+      PasteUrl::Html { name, url, deletion_key } => PasteUrl::Html { name: name, url: self.add_to_str(&url), deletion_key: deletion_key },
+      PasteUrl::Raw { name, url, deletion_key } => PasteUrl::Raw { name: name, url: self.add_to_str(&url), deletion_key: deletion_key },
+      PasteUrl::Downloaded(url, file) => PasteUrl::Downloaded(self.add_to_str(&url), file)
+    }
+  }
+
+  pub fn add_to_str(&self, url: &str) -> String {
+    let base = match url.find('#') {
+      Some(i) => &url[..i],
+      None => url
+    };
+    format!("{}#{}", base, self.fragment())
+  }
+
+  /// Encrypts a file, leaving its name intact. The encrypted content is always text, so it can be uploaded to any bin.
+  pub fn encrypt(&self, file: &UploadFile) -> Result<UploadFile> {
+    let key = SealingKey::new(&AES_256_GCM, &self.bytes).map_err(|_| ErrorKind::Msg("invalid encryption key".into()))?;
+    let mut nonce = vec![0; AES_256_GCM.nonce_len()];
+    SystemRandom::new().fill(&mut nonce).map_err(|_| ErrorKind::Msg("could not generate a nonce".into()))?;
+    let tag_len = AES_256_GCM.tag_len();
+    let mut in_out = file.content.clone();
+    in_out.extend(vec![0; tag_len]);
+    let len = aead::seal_in_place(&key, &nonce, &[], &mut in_out, tag_len)
+      .map_err(|_| ErrorKind::Msg(format!("could not encrypt {}", file.name)))?;
+    in_out.truncate(len);
+    nonce.extend(in_out);
+    let content = format!("{}{}\n", HEADER, base64::encode(&nonce));
+    Ok(UploadFile::new(file.name.clone(), content.into_bytes()))
+  }
+
+  /// Decrypts a downloaded file. Files that were not encrypted by bins are returned unchanged.
+  pub fn decrypt(&self, file: DownloadedFile) -> Result<DownloadedFile> {
+    if !file.content.starts_with(HEADER.as_bytes()) {
+      warn!("{} is not encrypted, so it was not decrypted", file.name.name());
+      return Ok(file);
+    }
+    let name = file.name.name();
+    let encoded = String::from_utf8_lossy(&file.content[HEADER.len()..]).trim().to_owned();
+    let mut data = base64::decode(&encoded).chain_err(|| format!("{} is not valid encrypted content", name))?;
+    let nonce_len = AES_256_GCM.nonce_len();
+    if data.len() < nonce_len + AES_256_GCM.tag_len() {
+      bail!("{} is too short to be encrypted content", name);
+    }
+    let mut ciphertext = data.split_off(nonce_len);
+    let key = OpeningKey::new(&AES_256_GCM, &self.bytes).map_err(|_| ErrorKind::Msg("invalid encryption key".into()))?;
+    let len = aead::open_in_place(&key, &data, &[], 0, &mut ciphertext)
+      .map_err(|_| ErrorKind::Msg(format!("could not decrypt {}. the key may be wrong or the paste may have been modified", name)))?
+      .len();
+    ciphertext.truncate(len);
+    Ok(DownloadedFile::new(file.name, ciphertext))
+  }
+}
@@ -47,6 +47,17 @@ error_chain! {
       description("an invalid file type was used as an input")
       display("bins stopped before uploading because {} is a disallowed file type ({})", name, kind)
     }
+    DisallowedFiles(files: Vec<String>) {
+      description("one or more inputs matched a disallowed file pattern")
+      display("bins stopped before uploading because {} matched a disallowed file pattern:\n{}",
+        if files.len() == 1 { "a file" } else { "some files" },
+        files.iter().map(|f| format!("  {}", f)).collect::<Vec<_>>().join("\n"))
+    }
+    SecretsFound(findings: Vec<String>) {
+      description("one or more inputs appeared to contain secrets")
+      display("bins stopped before uploading because the input appears to contain secrets:\n{}",
+        findings.iter().map(|f| format!("  {}", f)).collect::<Vec<_>>().join("\n"))
+    }
     Config {
       description("bins could not find a configuration file, and it was impossible to create one")
       display("bins could not find a configuration file, and it was impossible to create one")
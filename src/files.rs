@@ -1,3 +1,58 @@
+use error::*;
+
+use std::path::Path;
+
+/// File extensions and the languages they are highlighted as.
+const LANGUAGES: &'static [(&'static str, &'static str)] = &[
+  ("bash", "bash"),
+  ("c", "c"),
+  ("cc", "cpp"),
+  ("cpp", "cpp"),
+  ("cs", "csharp"),
+  ("css", "css"),
+  ("diff", "diff"),
+  ("go", "go"),
+  ("h", "c"),
+  ("hpp", "cpp"),
+  ("hs", "haskell"),
+  ("htm", "html"),
+  ("html", "html"),
+  ("ini", "ini"),
+  ("java", "java"),
+  ("js", "javascript"),
+  ("json", "json"),
+  ("kt", "kotlin"),
+  ("lua", "lua"),
+  ("md", "markdown"),
+  ("patch", "diff"),
+  ("php", "php"),
+  ("pl", "perl"),
+  ("py", "python"),
+  ("rb", "ruby"),
+  ("rs", "rust"),
+  ("scala", "scala"),
+  ("sh", "bash"),
+  ("sql", "sql"),
+  ("swift", "swift"),
+  ("toml", "toml"),
+  ("ts", "typescript"),
+  ("xml", "xml"),
+  ("yaml", "yaml"),
+  ("yml", "yaml"),
+  ("zsh", "bash")
+];
+
+/// Guesses the language of a file from the extension in its name.
+pub fn language_from_name(name: &str) -> Option<String> {
+  let extension = match Path::new(name).extension().and_then(|e| e.to_str()) {
+    Some(e) => e.to_lowercase(),
+    None => return None
+  };
+  LANGUAGES.iter()
+    .find(|&&(ext, _)| ext == extension)
+    .map(|&(_, lang)| lang.to_owned())
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexedFile {
   pub name: String,
@@ -13,10 +68,13 @@ impl IndexedFile {
   }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadFile {
   pub name: String,
-  pub content: String
+  #[serde(with = "content")]
+  pub content: Vec<u8>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub language: Option<String>
 }
 
 #[derive(Debug, Serialize)]
@@ -26,14 +84,15 @@ pub enum Paste {
   Multiple(Vec<DownloadedFile>),
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadedFile {
   pub name: PasteFileName,
-  pub content: String
+  #[serde(with = "content")]
+  pub content: Vec<u8>
 }
 
 impl DownloadedFile {
-  pub fn new(name: PasteFileName, content: String) -> DownloadedFile {
+  pub fn new(name: PasteFileName, content: Vec<u8>) -> DownloadedFile {
     DownloadedFile {
       name: name,
       content: content
@@ -41,7 +100,7 @@ impl DownloadedFile {
   }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PasteFileName {
   Explicit(String),
@@ -58,10 +117,52 @@ impl PasteFileName {
 }
 
 impl UploadFile {
-  pub fn new(name: String, content: String) -> UploadFile {
+  pub fn new(name: String, content: Vec<u8>) -> UploadFile {
     UploadFile {
       name: name,
-      content: content
+      content: content,
+      language: None
+    }
+  }
+
+  pub fn is_binary(&self) -> bool {
+    ::std::str::from_utf8(&self.content).is_err()
+  }
+
+  pub fn text(&self) -> Result<&str> {
+    ::std::str::from_utf8(&self.content).chain_err(|| format!("{} is not valid utf-8", self.name))
+  }
+}
+
+/// Serializes file content as a string if it is valid UTF-8, or as `{"base64": "..."}` otherwise.
+pub(crate) mod content {
+  use serde::{Serialize, Serializer, Deserialize, Deserializer};
+  use serde::de::Error;
+  use base64;
+
+  #[derive(Serialize, Deserialize)]
+  #[serde(untagged)]
+  enum Content {
+    Text(String),
+    Binary { base64: String }
+  }
+
+  pub fn serialize<S>(content: &Vec<u8>, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer
+  {
+    let content = match ::std::str::from_utf8(content) {
+      Ok(s) => Content::Text(s.to_owned()),
+      Err(_) => Content::Binary { base64: base64::encode(content) }
+    };
+    content.serialize(serializer)
+  }
+
+  pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+    where D: Deserializer<'de>
+  {
+    match Content::deserialize(deserializer)? {
+      Content::Text(s) => Ok(s.into_bytes()),
+      Content::Binary { base64: b } => base64::decode(&b).map_err(D::Error::custom)
     }
   }
 }
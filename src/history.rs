@@ -0,0 +1,115 @@
+use serde_json;
+use ring::digest::{self, SHA256};
+use time::{self, Timespec};
+
+use lib::{PasteUrl, Result};
+use lib::error::*;
+use lib::files::UploadFile;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryRecord {
+  pub bin: String,
+  pub urls: Vec<PasteUrl>,
+  pub files: Vec<HistoryFile>,
+  pub private: bool,
+  pub timestamp: i64,
+  pub alias: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryFile {
+  pub name: String,
+  pub size: u64,
+  pub sha256: String
+}
+
+impl HistoryRecord {
+  pub fn new(bin: &str, urls: Vec<PasteUrl>, files: &[UploadFile], private: bool, alias: Option<String>) -> HistoryRecord {
+    HistoryRecord {
+      bin: bin.to_owned(),
+      urls: urls,
+      files: files.iter().map(HistoryFile::new).collect(),
+      private: private,
+      timestamp: time::get_time().sec,
+      alias: alias
+    }
+  }
+
+  pub fn time(&self) -> String {
+    let tm = time::at(Timespec::new(self.timestamp, 0));
+    tm.strftime("%Y-%m-%d %H:%M:%S").map(|t| t.to_string()).unwrap_or_else(|_| self.timestamp.to_string())
+  }
+
+  /// Checks if the bin, any URL, or any file name contains the query.
+  pub fn matches(&self, query: &str) -> bool {
+    let query = query.to_lowercase();
+    self.bin.to_lowercase().contains(&query) ||
+      self.urls.iter().any(|u| u.url().to_lowercase().contains(&query)) ||
+      self.files.iter().any(|f| f.name.to_lowercase().contains(&query))
+  }
+}
+
+impl HistoryFile {
+  fn new(file: &UploadFile) -> HistoryFile {
+    let hash = digest::digest(&SHA256, &file.content);
+    HistoryFile {
+      name: file.name.clone(),
+      size: file.content.len() as u64,
+      sha256: hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+  }
+}
+
+/// The history of uploads, stored as one JSON record per line.
+pub struct History {
+  path: PathBuf
+}
+
+impl History {
+  pub fn new(path: PathBuf) -> History {
+    History {
+      path: path
+    }
+  }
+
+  /// Loads every record, oldest first.
+  pub fn load(&self) -> Result<Vec<HistoryRecord>> {
+    if !self.path.exists() {
+      return Ok(Vec::new());
+    }
+    let file = File::open(&self.path).chain_err(|| "could not open history file")?;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+      let line = line?;
+      if line.trim().is_empty() {
+        continue;
+      }
+      records.push(serde_json::from_str(&line).chain_err(|| "could not parse history file")?);
+    }
+    Ok(records)
+  }
+
+  pub fn append(&self, record: &HistoryRecord) -> Result<()> {
+    let json = serde_json::to_string(record)?;
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    private_file(&mut options);
+    let mut file = options.open(&self.path).chain_err(|| "could not open history file")?;
+    writeln!(file, "{}", json).chain_err(|| "could not write to history file")
+  }
+}
+
+/// The history lists every paste, including private ones, so only the user can read it.
+#[cfg(unix)]
+fn private_file(options: &mut OpenOptions) {
+  options.mode(0o600);
+}
+
+#[cfg(not(unix))]
+fn private_file(_: &mut OpenOptions) {}
@@ -3,28 +3,64 @@
 
 extern crate url;
 extern crate hyper;
+#[cfg(feature = "openssl")]
+extern crate hyper_openssl;
+#[cfg(feature = "rustls")]
+extern crate hyper_rustls;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate base64;
 extern crate toml;
+extern crate flate2;
 #[macro_use]
 extern crate log;
+extern crate time;
 extern crate scoped_threadpool;
 extern crate num_cpus;
 #[cfg(feature = "file_type_checking")]
 extern crate magic;
 #[macro_use]
 extern crate error_chain;
+extern crate rand;
+extern crate uuid;
+extern crate regex;
+extern crate glob;
+extern crate ring;
+extern crate aes;
+extern crate aes_gcm;
+extern crate bs58;
+extern crate lazycell;
+
+macro_rules! option {
+  ($e: expr) => {{
+    match $e {
+      Some(x) => x,
+      None => return None
+    }
+  }}
+}
 
 pub mod error;
 pub mod files;
 pub mod range;
+pub mod transport;
+pub mod units;
+pub mod config;
+pub mod scan;
+pub mod redact;
+pub mod encrypt;
+pub mod bins;
+pub mod client;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
 
 use error::*;
 use range::{BidirectionalRange, AnyContains};
 use files::*;
-
-use hyper::Client;
+use transport::HttpClient;
 
 use scoped_threadpool::Pool;
 
@@ -33,8 +69,9 @@ use std::sync::mpsc::channel;
 use std::collections::HashMap;
 
 pub use error::Result;
+pub use client::{Client, Upload, upload};
 
-pub trait Bin: Uploads + Downloads + ManagesUrls + HasFeatures {
+pub trait Bin: Uploads + Updates + Downloads + Deletes + ManagesUrls + HasFeatures {
   fn name(&self) -> &str;
 
   fn html_host(&self) -> &str;
@@ -76,6 +113,13 @@ pub trait Uploads {
   fn upload(&self, contents: &[UploadFile], index: bool) -> Result<Vec<PasteUrl>>;
 }
 
+/// Bins that have `BinFeature::Updatable` implement `update`. For any other bin, updating a paste is an error.
+pub trait Updates {
+  fn update(&self, _: &str, _: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    bail!("this bin does not support updating pastes")
+  }
+}
+
 pub trait UploadsSingleFiles {
   fn upload_single(&self, content: &UploadFile) -> Result<PasteUrl>;
 }
@@ -84,8 +128,26 @@ pub trait Downloads {
   fn download(&self, id: &str, info: &DownloadInfo) -> Result<Paste>;
 }
 
+/// Bins that have `BinFeature::Deletable` implement `delete`. For any other bin, deleting a paste is an error.
+pub trait Deletes {
+  fn delete(&self, _: &str) -> Result<()> {
+    bail!("this bin does not support deleting pastes")
+  }
+}
+
 pub trait HasClient {
-  fn client(&self) -> &Client;
+  fn client(&self) -> &HttpClient;
+
+  /// Makes the bin send its requests with `client`, such as a client that answers from fixtures with a `Replay`.
+  fn set_client(&mut self, client: HttpClient);
+
+  /// `set_client` for a bin that is being created.
+  fn with_client(mut self, client: HttpClient) -> Self
+    where Self: Sized
+  {
+    self.set_client(client);
+    self
+  }
 }
 
 impl<T> Uploads for T
@@ -129,14 +191,11 @@ impl<T> Uploads for T
       debug!("creating index");
       let index = serde_json::to_string_pretty(&urls)?;
       debug!("uploading index");
-      self.upload_single(&UploadFile::new("index.json".to_owned(), index)).map(|x| vec![x])
+      self.upload_single(&UploadFile::new("index.json".to_owned(), index.into_bytes())).map(|x| vec![x])
     } else {
       Ok(urls.into_iter()
         .map(|indexed_file| {
-          PasteUrl::Html {
-            name: Some(PasteFileName::Explicit(indexed_file.name)),
-            url: indexed_file.url
-          }
+          PasteUrl::html(Some(PasteFileName::Explicit(indexed_file.name)), indexed_file.url)
         })
         .collect())
     }
@@ -182,7 +241,7 @@ impl<T> Downloads for T
     let mut pool = Pool::new(num_cpus::get() as u32);
     let channel_size = raw_url_strs.len();
     let mut contents = Vec::with_capacity(channel_size);
-    pool.scoped(|scope| {
+    let res: Result<()> = pool.scoped(|scope| {
       for (i, url) in raw_url_strs.into_iter().enumerate() {
         let tx_clone = tx.clone();
         debug!("queuing scoped download thread");
@@ -214,9 +273,7 @@ impl<T> Downloads for T
             return;
           } else {
             debug!("downloading {:?}", url);
-            let mut res = match self.client().get(url.url())
-              .send()
-              .map_err(ErrorKind::Http) {
+            let mut res = match self.client().get(url.url()).send() {
               Ok(r) => r,
               Err(e) => {
                 if let Err(tx_e) = tx_clone.send(Err(e)) {
@@ -225,8 +282,8 @@ impl<T> Downloads for T
                 return;
               }
             };
-            let mut content = String::new();
-            if let Err(e) = res.read_to_string(&mut content).map_err(ErrorKind::Io) {
+            let mut content = Vec::new();
+            if let Err(e) = res.read_to_end(&mut content).map_err(Error::from) {
               if let Err(tx_e) = tx_clone.send(Err(e)) {
                 error!("error sending result over channel: {}", tx_e);
               }
@@ -234,7 +291,7 @@ impl<T> Downloads for T
             }
             if res.status.class().default_code() != ::hyper::Ok {
               debug!("bad status code");
-              let e = ErrorKind::InvalidStatus(res.status_raw().0, Some(content));
+              let e = ErrorKind::InvalidStatus(res.status_raw().0, Some(String::from_utf8_lossy(&content).into_owned())).into();
               if let Err(tx_e) = tx_clone.send(Err(e)) {
                 error!("error sending result over channel: {}", tx_e);
               }
@@ -280,14 +337,15 @@ impl<T> Downloads for T
         for key in keys {
           let file = match map.remove(&key) {
             Some(x) => x,
-            None => return Err(ErrorKind::Other)
+            None => return Err(ErrorKind::Other.into())
           };
           contents.push(file);
         }
         contents.sort_by_key(|f| f.name.name());
       }
       Ok(())
-    })?;
+    });
+    res?;
     debug!("contents downloaded: {:?}", contents);
     if contents.is_empty() {
       debug!("no files downloaded. displaying filter error");
@@ -306,14 +364,25 @@ impl<T> Downloads for T
   }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum BinFeature {
   Private,
   Public,
   Authed,
   Anonymous,
   MultiFile,
-  SingleNaming
+  SingleNaming,
+  Deletable,
+  Updatable,
+  BinaryFiles,
+  Expiring,
+  BurnAfterReading,
+  Titled,
+  Described,
+  PasswordProtected,
+  Discussion,
+  Encrypted
 }
 
 impl ::std::fmt::Display for BinFeature {
@@ -324,22 +393,36 @@ impl ::std::fmt::Display for BinFeature {
       BinFeature::Authed => "authed",
       BinFeature::Anonymous => "anonymous",
       BinFeature::MultiFile => "multiple-file",
-      BinFeature::SingleNaming => "single-file named"
+      BinFeature::SingleNaming => "single-file named",
+      BinFeature::Deletable => "deletable",
+      BinFeature::Updatable => "updatable",
+      BinFeature::BinaryFiles => "binary-file",
+      BinFeature::Expiring => "expiring",
+      BinFeature::BurnAfterReading => "burn-after-reading",
+      BinFeature::Titled => "titled",
+      BinFeature::Described => "described",
+      BinFeature::PasswordProtected => "password-protected",
+      BinFeature::Discussion => "discussion",
+      BinFeature::Encrypted => "encrypted"
     };
     write!(f, "{}", desc)
   }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum PasteUrl {
   Html {
     name: Option<PasteFileName>,
-    url: String
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deletion_key: Option<String>
   },
   Raw {
     name: Option<PasteFileName>,
-    url: String
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deletion_key: Option<String>
   },
   Downloaded(String, DownloadedFile)
 }
@@ -348,15 +431,26 @@ impl PasteUrl {
   pub fn html(name: Option<PasteFileName>, url: String) -> PasteUrl {
     PasteUrl::Html {
       name: name,
-      url: url
+      url: url,
+      deletion_key: None
     }
   }
 
   pub fn raw(name: Option<PasteFileName>, url: String) -> PasteUrl {
     PasteUrl::Raw {
       name: name,
-      url: url
+      url: url,
+      deletion_key: None
+    }
+  }
+
+  pub fn with_deletion_key(mut self, key: String) -> PasteUrl {
+    match self {
+      PasteUrl::Html { ref mut deletion_key, .. } |
+      PasteUrl::Raw { ref mut deletion_key, .. } => *deletion_key = Some(key),
+      PasteUrl::Downloaded(..) => {}
     }
+    self
   }
 
   pub fn name(&self) -> Option<PasteFileName> {
@@ -374,4 +468,12 @@ impl PasteUrl {
       PasteUrl::Downloaded(ref url, _) => url
     }
   }
+
+  pub fn deletion_key(&self) -> Option<&str> {
+    match *self {
+      PasteUrl::Html { ref deletion_key, .. } |
+      PasteUrl::Raw { ref deletion_key, .. } => deletion_key.as_ref().map(|x| x.as_str()),
+      PasteUrl::Downloaded(..) => None
+    }
+  }
 }
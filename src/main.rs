@@ -1,31 +1,19 @@
-#![feature(box_syntax)]
-
 extern crate bins as lib;
-extern crate hyper;
-#[cfg(feature = "openssl")]
-extern crate hyper_openssl;
-#[cfg(feature = "rustls")]
-extern crate hyper_rustls;
 extern crate url;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
 #[macro_use]
 extern crate clap;
-extern crate toml;
-extern crate flate2;
 #[macro_use]
 extern crate log;
 extern crate time;
-#[cfg(feature = "file_type_checking")]
-extern crate magic;
 #[cfg(feature = "clipboard_support")]
 extern crate clipboard;
-extern crate rand;
-extern crate base64;
 #[macro_use]
 extern crate error_chain;
-extern crate uuid;
+extern crate ring;
 
 macro_rules! option {
   ($e: expr) => {{
@@ -41,31 +29,28 @@ macro_rules! option {
 // TODO: refactor inner
 // TODO: investigate -v vs --version
 
-mod bins;
-mod config;
 mod logger;
 mod cli;
 mod json;
+mod history;
 
-use config::*;
+use history::{History, HistoryRecord};
 
 use lib::*;
 use lib::error::*;
-use lib::files::{Paste, UploadFile};
+use lib::files::{DownloadedFile, Paste, UploadFile, language_from_name};
+use lib::config::*;
+use lib::encrypt::Key;
 use lib::range::BidirectionalRange;
+use lib::units::parse_duration;
 
 use clap::ArgMatches;
-use flate2::read::GzDecoder;
-use hyper::Client;
-use hyper::net::HttpsConnector;
 
 use std::path::{Path, PathBuf};
 use std::fs::{File, OpenOptions};
-use std::io::{Seek, SeekFrom};
 use std::io::{Read, Write};
 use std::io::Result as IoResult;
 use std::error::Error;
-use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 use log::LogLevel;
@@ -104,7 +89,7 @@ fn main() {
 }
 
 fn inner() -> i32 {
-  let config = match get_config() {
+  let config = match Config::load() {
     Ok(c) => c,
     Err(e) => {
       report_error_using!(println, "could not create or load bins config file: {}", &e);
@@ -136,7 +121,7 @@ fn inner() -> i32 {
     return 1;
   }
 
-  if !matches.is_present("list-bins") && !config.defaults.bin.is_some() && !matches.is_present("bin") {
+  if !matches.is_present("list-bins") && !matches.is_present("update") && matches.subcommand_name().is_none() && !config.defaults.bin.is_some() && !matches.is_present("bin") {
     error!("you must specify a bin with --bin or set a default bin");
     return 1;
   }
@@ -153,7 +138,7 @@ fn inner() -> i32 {
     cli_options.authed = Some(false);
   }
 
-  if matches.is_present("json") {
+  if matches.is_present("json") || matches.subcommand_matches("history").map(|m| m.is_present("json")).unwrap_or(false) {
     cli_options.json = Some(true);
   }
 
@@ -161,6 +146,14 @@ fn inner() -> i32 {
     cli_options.force = Some(true);
   }
 
+  if matches.is_present("redact") {
+    cli_options.redact = Some(true);
+  }
+
+  if matches.is_present("encrypt") {
+    cli_options.encrypt = Some(true);
+  }
+
   if matches.is_present("list-all") {
     cli_options.list_all = Some(true);
   }
@@ -176,6 +169,48 @@ fn inner() -> i32 {
     }
   }
 
+  if let Some(expires) = matches.value_of("expires").or_else(|| config.defaults.expires.as_ref().map(|x| x.as_str())) {
+    match parse_duration(expires) {
+      Ok(d) => cli_options.expires = Some(d),
+      Err(e) => {
+        report_error!(cli_options.json(), "error parsing expiry: {}", &e);
+        return 1;
+      }
+    }
+  }
+
+  if matches.is_present("burn") {
+    cli_options.burn = Some(true);
+  }
+
+  if let Some(password) = matches.value_of("password") {
+    cli_options.password = Some(password.to_owned());
+  }
+
+  if matches.is_present("discussion") {
+    cli_options.discussion = Some(true);
+  }
+
+  if let Some(title) = matches.value_of("title").or_else(|| config.defaults.title.as_ref().map(|x| x.as_str())) {
+    cli_options.title = Some(title.to_owned());
+  }
+
+  if let Some(description) = matches.value_of("description").or_else(|| config.defaults.description.as_ref().map(|x| x.as_str())) {
+    cli_options.description = Some(description.to_owned());
+  }
+
+  if let Some(alias) = matches.value_of("alias") {
+    if alias == "last" || alias.parse::<usize>().is_ok() || alias.starts_with('@') {
+      error!("\"{}\" cannot be used as an alias", alias);
+      return 1;
+    }
+    cli_options.alias = Some(alias.to_owned());
+  }
+
+  if let Some(language) = matches.value_of("lang") {
+    cli_options.language = Some(language.to_owned());
+  }
+
   if let Some(name) = matches.value_of("name") {
     cli_options.name = Some(name.to_owned());
   }
@@ -184,6 +219,12 @@ fn inner() -> i32 {
     cli_options.output = Some(path.to_owned());
   }
 
+  if let Some(delete) = matches.subcommand_matches("delete") {
+    if let Some(key) = delete.value_of("key") {
+      cli_options.deletion_key = Some(key.to_owned());
+    }
+  }
+
   if matches.is_present("raw-urls") {
     cli_options.url_output = Some(UrlOutputMode::Raw);
   } else if matches.is_present("html-urls") {
@@ -202,21 +243,16 @@ fn inner() -> i32 {
   let config = Arc::new(config);
   let cli_options = Arc::new(cli_options);
 
-  let bins: BTreeMap<String, Box<Bin>> = {
-    let bins: Vec<Box<Bin>> = vec![
-      box bins::Sprunge::new(),
-      box bins::Hastebin::new(),
-      box bins::Fedora::new(),
-      box bins::Gist::new(config.clone(), cli_options.clone()),
-      box bins::Bitbucket::new(config.clone(), cli_options.clone()),
-      box bins::Pastebin::new(config.clone(), cli_options.clone()),
-      box bins::PasteGg::new(config.clone(), cli_options.clone()),
-    ];
-    bins.into_iter().map(|b| (b.name().to_owned(), b)).collect()
+  let client = match Client::new(config.clone(), cli_options.clone()) {
+    Ok(c) => c,
+    Err(e) => {
+      report_error!(cli_options.json(), "{}", &e);
+      return 1;
+    }
   };
 
   let b = Bins {
-    bins: bins,
+    client: client,
     config: config,
     cli_options: cli_options,
     matches: matches
@@ -298,7 +334,7 @@ fn print_version() {
 }
 
 struct Bins<'a> {
-  bins: BTreeMap<String, Box<Bin>>,
+  client: Client,
   config: Arc<Config>,
   cli_options: Arc<CommandLineOptions>,
   matches: ArgMatches<'a>
@@ -309,12 +345,24 @@ impl<'a> Bins<'a> {
     if self.matches.is_present("list-bins") {
       return self.list_bins();
     }
+    if let Some(history) = self.matches.subcommand_matches("history") {
+      let limit = match history.value_of("limit") {
+        Some(l) => Some(l.parse::<usize>().chain_err(|| format!("{} is not a valid limit", l))?),
+        None => None
+      };
+      return self.history(history.value_of("query"), limit);
+    }
+    if let Some(delete) = self.matches.subcommand_matches("delete") {
+      let url = delete.value_of("url").ok_or_else(|| ErrorKind::Msg("no url was specified".into()))?;
+      return self.delete(url);
+    }
     let inputs = self.raw_inputs();
+    if let Some(url) = self.matches.value_of("update") {
+      return self.update(url, inputs);
+    }
     if let Some(ref is) = inputs {
-      if !is.is_empty() {
-        if let Ok(u) = Url::parse(is[0]) {
-          return self.download(u, if is.len() > 1 { Some(&is[1..]) } else { None }); // FIXME
-        }
+      if !is.is_empty() && (is_reference(is[0]) || Url::parse(is[0]).is_ok()) {
+        return self.download(is[0], if is.len() > 1 { Some(&is[1..]) } else { None }); // FIXME
       }
     }
     if self.cli_options.range.is_some() {
@@ -323,65 +371,19 @@ impl<'a> Bins<'a> {
     self.upload(inputs)
   }
 
-  fn file_size_limit(&self) -> Result<Option<u64>> {
-    let s = match self.config.general.file_size_limit {
-      Some(ref x) => x,
-      None => return Ok(None)
-    };
-    let mut size: Vec<char> = Vec::new();
-    let mut unit: Vec<char> = Vec::new();
-    for c in s.trim().chars() {
-      if "0123456789.".contains(c) {
-        if !unit.is_empty() {
-          bail!("the file size limit specified in the config is invalid");
-        }
-        size.push(c);
-      } else if "bBkKmMgGiI".contains(c) {
-        unit.push(c);
-      }
-    }
-    let size: f64 = size.into_iter().collect::<String>().parse().chain_err(|| "the file size limit specified in the config is invalid")?;
-    let unit = unit.into_iter().collect::<String>().to_lowercase();
-    let unit = if unit.is_empty() {
-      1
-    } else {
-      match unit.as_str() {
-        "b" => 1,
-        "kb" => (10 as u64).pow(3),
-        "kib" => (2 as u64).pow(10),
-        "mb" => (10 as u64).pow(6),
-        "mib" => (2 as u64).pow(20),
-        "gb" => (10 as u64).pow(9),
-        "gib" => (2 as u64).pow(30),
-        _ => bail!("the file size limit specified in the config is invalid")
-      }
-    };
-    Ok(Some((size * unit as f64).round() as u64))
-  }
-
   fn raw_inputs(&self) -> Option<Vec<&str>> {
     self.matches.values_of("inputs").map(|x| x.collect())
   }
 
   fn list_bins(&self) -> Result<String> {
     if let Some(true) = self.cli_options.json {
-      let names: Vec<&String> = self.bins.keys().collect();
+      let names: Vec<&String> = self.client.bins().keys().collect();
       serde_json::to_string(&names).chain_err(|| "could not serialize list of bins")
     } else {
-      Ok(self.bins.keys().cloned().collect::<Vec<_>>().join("\n"))
+      Ok(self.client.bins().keys().cloned().collect::<Vec<_>>().join("\n"))
     }
   }
 
-  fn cli_features(&self) -> HashMap<BinFeature, Option<bool>> {
-    let mut map = HashMap::new();
-    map.insert(BinFeature::Private, self.cli_options.private);
-    map.insert(BinFeature::Public, self.cli_options.private.map(|x| !x));
-    map.insert(BinFeature::Authed, self.cli_options.authed);
-    map.insert(BinFeature::Anonymous, self.cli_options.authed.map(|x| !x));
-    map.insert(BinFeature::SingleNaming, self.cli_options.name.as_ref().map(|_| true));
-    map
-  }
-
   fn bin_name(&self) -> Result<String> {
     self.matches.value_of("bin")
       .map(|x| x.to_owned())
@@ -391,62 +393,21 @@ impl<'a> Bins<'a> {
   }
 
   fn bin(&self) -> Result<&Box<Bin>> {
-    let name = self.bin_name()?;
-    self.bins.get(&name).ok_or_else(|| format!("there is no bin called \"{}\"", name).into())
-  }
-
-  fn check_features(&self, bin: &Bin) -> Result<()> {
-    let bin_features = bin.features();
-    let features = self.cli_features();
-    for (feature, status) in features {
-      if let Some(true) = status {
-        if !bin_features.contains(&feature) {
-          if let Some(true) = self.config.safety.warn_on_unsupported {
-            warn!("{} does not support {} pastes", bin.name(), feature);
-          }
-          if let Some(true) = self.config.safety.cancel_on_unsupported {
-            return match self.cli_options.force {
-              Some(true) => {
-                warn!("forcing upload with unsupported features");
-                Ok(())
-              },
-              _ => bail!("bins stopped because {} does not support {} pastes", bin.name(), feature)
-            }
-          }
-        }
-      }
-    }
-    Ok(())
+    self.client.bin(&self.bin_name()?)
   }
 
   fn check_limit(&self, files: &[(&str, File)]) -> Result<()> {
-    let limit = match self.file_size_limit()? {
-      Some(l) => l,
-      None => return Ok(())
-    };
-
     for &(name, ref file) in files {
-      let metadata = file.metadata()?;
-      let size = metadata.len();
-      if size > limit {
-        if let Some(true) = self.cli_options.force {
-          warn!("{} is {} bytes, which is over the {} byte limit", name, size, limit);
-        } else {
-          bail!("{} is {} byte{}, which is over the size limit of {} byte{}",
-            name,
-            size,
-            if size == 1 { "" } else { "s" },
-            limit,
-            if limit == 1 { "" } else { "s" });
-        }
-      }
+      self.client.check_size(name, file.metadata()?.len())?;
     }
     Ok(())
   }
 
   fn get_upload_files(&self, inputs: Vec<&str>) -> Result<Vec<UploadFile>> {
-    let files: Option<Vec<(&str, File)>> = inputs.into_iter()
-      .map(|f| File::open(f).map(|x| Path::new(f).file_name().and_then(|f| f.to_str()).map(|of| (of, x))))
+    let inputs: Vec<(&str, Option<&str>)> = inputs.into_iter().map(split_language).collect();
+    self.client.check_file_patterns(&inputs.iter().map(|&(f, _)| f).collect::<Vec<_>>())?;
+    let files: Option<Vec<(&str, File)>> = inputs.iter()
+      .map(|&(f, _)| File::open(f).map(|x| Path::new(f).file_name().and_then(|f| f.to_str()).map(|of| (of, x))))
       .collect::<IoResult<_>>()?;
     let files = match files {
       Some(f) => f,
@@ -456,13 +417,20 @@ impl<'a> Bins<'a> {
       }
     };
     self.check_limit(&files)?;
-    let contents: Vec<(&str, String)> = files.into_iter()
+    let contents: Vec<(&str, Vec<u8>)> = files.into_iter()
       .map(|(n, mut f)| {
-        let mut c = String::new();
-        f.read_to_string(&mut c).map(|_| (n, c))
+        let mut c = Vec::new();
+        f.read_to_end(&mut c).map(|_| (n, c))
       })
       .collect::<IoResult<_>>()?;
-    Ok(contents.into_iter().map(|(n, c)| UploadFile::new(n.to_owned(), c)).collect())
+    Ok(contents.into_iter()
+      .zip(inputs)
+      .map(|((n, c), (_, l))| {
+        let mut file = UploadFile::new(n.to_owned(), c);
+        file.language = l.map(|x| x.to_owned());
+        file
+      })
+      .collect())
   }
 
   fn inputs(&self, inputs: Option<Vec<&str>>) -> Result<Vec<UploadFile>> {
@@ -470,7 +438,7 @@ impl<'a> Bins<'a> {
       Some(v) => self.get_upload_files(v),
       None => {
         if let Some(message) = self.matches.value_of("message") {
-          Ok(vec![UploadFile::new(String::from("message"), message.to_owned())])
+          Ok(vec![UploadFile::new(String::from("message"), message.as_bytes().to_vec())])
         } else {
           get_stdin().map(|x| vec![x])
         }
@@ -483,6 +451,14 @@ impl<'a> Bins<'a> {
         bail!("cannot use --name with multiple upload files");
       }
     }
+    for file in &mut processed {
+      if file.language.is_none() {
+        file.language = self.cli_options.language.clone().or_else(|| language_from_name(&file.name));
+      }
+    }
+    if let Some(true) = self.cli_options.redact {
+      self.client.redact(&mut processed)?;
+    }
     Ok(processed)
   }
 
@@ -506,61 +482,180 @@ impl<'a> Bins<'a> {
 
   fn upload(&self, inputs: Option<Vec<&str>>) -> Result<String> {
     let bin = self.bin()?;
-    self.check_features(bin.as_ref())?;
+    self.client.check_features(bin.as_ref())?;
 
     let upload_files = self.inputs(inputs)?;
     #[cfg(feature = "file_type_checking")]
-    self.check_file_types(&upload_files)?;
-    let urls = bin.upload(&upload_files, self.cli_options.url_output.is_none())?;
-    if let Some(UrlOutputMode::Raw) = self.cli_options.url_output {
-      return self.url_output(bin.as_ref(), &urls);
-    }
-    Ok(urls.into_iter().map(|u| u.url().to_string()).collect::<Vec<String>>().join("\n"))
-  }
-
-  #[cfg(feature = "file_type_checking")]
-  fn check_file_types(&self, files: &[UploadFile]) -> Result<()> {
-    use magic::{Cookie, flags};
-
-    let cookie = Cookie::open(flags::NONE)?;
-    cookie.load(&[""; 0])?;
-    for upload_file in files {
-      let kind = cookie.buffer(upload_file.content.as_bytes())?;
-      if let Some(ref disallowed) = self.config.safety.disallowed_file_types {
-        if disallowed.contains(&kind) {
-          return match self.cli_options.force {
-            Some(true) => {
-              warn!("forcing upload with disallowed file type: ({} is {}, which is disallowed)", upload_file.name, kind);
-              Ok(())
-            },
-            _ => Err(ErrorKind::InvalidFileType(upload_file.name.clone(), kind).into())
-          }
-        }
+    self.client.check_file_types(&upload_files)?;
+    self.client.check_secrets(&upload_files)?;
+    let key = self.client.new_key(bin.as_ref())?;
+    let encrypted = self.client.encrypt(key.as_ref(), &upload_files)?;
+    let sent_files = encrypted.as_ref().unwrap_or(&upload_files);
+    self.client.check_binary(bin.as_ref(), sent_files)?;
+    let urls = bin.upload(sent_files, self.cli_options.url_output.is_none())?;
+    for url in &urls {
+      if let Some(key) = url.deletion_key() {
+        info!("deletion key for {}: {}", url.url(), key);
       }
     }
-    Ok(())
+    let output = if let Some(UrlOutputMode::Raw) = self.cli_options.url_output {
+      self.url_output(bin.as_ref(), &urls)?
+    } else {
+      urls.iter().map(|u| u.url().to_string()).collect::<Vec<String>>().join("\n")
+    };
+    let (urls, output) = match key {
+      Some(ref k) => (urls.into_iter().map(|u| k.add_to(u)).collect(), add_key(k, &output)),
+      None => (urls, output)
+    };
+    self.record_history(bin.as_ref(), urls, &upload_files);
+    Ok(output)
   }
 
-  fn download(&self, url: Url, names: Option<&[&str]>) -> Result<String> {
-    if names.is_some() && self.cli_options.range.is_some() {
-      bail!("cannot specify file names with --range");
+  fn history_file(&self) -> Option<History> {
+    if let Some(false) = self.config.general.history {
+      return None;
     }
-    let host = url.host_str().ok_or_else(|| ErrorKind::Msg("url was missing a host".into()))?;
-    let (is_html_url, bin) = match self.bins.iter().find(|&(_, b)| b.raw_host() == host) {
-      Some(b) => (false, b.1),
-      None => {
-        match self.bins.iter().find(|&(_, b)| b.html_host() == host) {
-          Some(b) => (true, b.1),
-          None => bail!("no bin uses the hostname {}", host)
-        }
-      }
+    find_history_path().map(History::new)
+  }
+
+  fn record_history(&self, bin: &Bin, urls: Vec<PasteUrl>, files: &[UploadFile]) {
+    let history = match self.history_file() {
+      Some(h) => h,
+      None => return
+    };
+    let private = self.cli_options.private.or(self.config.defaults.private).unwrap_or(false);
+    let record = HistoryRecord::new(bin.name(), urls, files, private, self.cli_options.alias.clone());
+    if let Err(e) = history.append(&record) {
+      report_error_using!(warn, "could not record upload in history: {}", &e);
+    }
+  }
+
+  fn history(&self, query: Option<&str>, limit: Option<usize>) -> Result<String> {
+    let history = match self.history_file() {
+      Some(h) => h,
+      None => bail!("upload history is disabled or there is no configuration directory")
     };
-    let id = if is_html_url {
-      bin.id_from_html_url(url.as_str())
+    let records: Vec<HistoryRecord> = history.load()?
+      .into_iter()
+      .rev()
+      .filter(|r| query.map(|q| r.matches(q)).unwrap_or(true))
+      .take(limit.unwrap_or(::std::usize::MAX))
+      .collect();
+    if self.cli_options.json() {
+      return serde_json::to_string(&records).chain_err(|| "could not serialize history");
+    }
+    Ok(records.iter()
+      .map(|r| format!("{}  {}{}  {}  {}",
+        r.time(),
+        r.alias.as_ref().map(|a| format!("@{}  ", a)).unwrap_or_default(),
+        r.bin,
+        r.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(", "),
+        r.urls.iter().map(|u| u.url()).collect::<Vec<_>>().join(" ")))
+      .collect::<Vec<_>>()
+      .join("\n"))
+  }
+
+  fn update(&self, url: &str, inputs: Option<Vec<&str>>) -> Result<String> {
+    let (bin, id, key) = self.locate(url)?;
+    if !bin.features().contains(&BinFeature::Updatable) {
+      bail!("{} does not support updating pastes", bin.name());
+    }
+    self.client.check_features(bin.as_ref())?;
+
+    let upload_files = self.inputs(inputs)?;
+    #[cfg(feature = "file_type_checking")]
+    self.client.check_file_types(&upload_files)?;
+    self.client.check_secrets(&upload_files)?;
+    // files added to an encrypted paste are encrypted with the same key, so the paste's URL keeps working
+    let key = match key {
+      Some(k) => Some(k),
+      None => self.client.new_key(bin.as_ref())?
+    };
+    let encrypted = self.client.encrypt(key.as_ref(), &upload_files)?;
+    let sent_files = encrypted.as_ref().unwrap_or(&upload_files);
+    self.client.check_binary(bin.as_ref(), sent_files)?;
+    let urls = bin.update(&id, sent_files)?;
+    let output = if let Some(UrlOutputMode::Raw) = self.cli_options.url_output {
+      self.url_output(bin.as_ref(), &urls)?
     } else {
-      bin.id_from_raw_url(url.as_str())
+      urls.into_iter().map(|u| u.url().to_string()).collect::<Vec<String>>().join("\n")
+    };
+    Ok(match key {
+      Some(ref k) => add_key(k, &output),
+      None => output
+    })
+  }
+
+  /// Finds the bin and ID for either a URL or a reference to a previous upload, along with the encryption key in the
+  /// URL's fragment, if any.
+  fn locate(&self, input: &str) -> Result<(&Box<Bin>, String, Option<Key>)> {
+    if !is_reference(input) {
+      return self.client.locate(input);
+    }
+    let record = self.resolve_reference(&input[1..])?;
+    let bin = self.client.bins().get(&record.bin)
+      .ok_or_else(|| ErrorKind::Msg(format!("{} was uploaded to {}, which is not available", input, record.bin)))?;
+    let url = match record.urls.first() {
+      Some(u) => u,
+      None => bail!("{} has no URLs", input)
+    };
+    let key = Key::from_url(url.url())?;
+    let url_str = match key {
+      Some(_) => url.url().split('#').next().unwrap_or_default(),
+      None => url.url()
+    };
+    let id = match *url {
+      PasteUrl::Raw { .. } => bin.id_from_raw_url(url_str),
+      _ => bin.id_from_html_url(url_str)
     };
     let id = id.ok_or_else(|| ErrorKind::Msg("could not parse ID from URL".into()))?;
+    debug!("{} refers to {} on {}", input, url_str, record.bin);
+    Ok((bin, id, key))
+  }
+
+  /// Resolves `last`, a number counting back from the latest upload (`1` is the latest), or an alias.
+  fn resolve_reference(&self, reference: &str) -> Result<HistoryRecord> {
+    let history = match self.history_file() {
+      Some(h) => h,
+      None => bail!("cannot use @{} because upload history is disabled", reference)
+    };
+    let mut records = history.load()?;
+    records.reverse();
+    let index = if reference == "last" {
+      Some(0)
+    } else {
+      match reference.parse::<usize>() {
+        Ok(0) => bail!("@0 does not refer to an upload. the latest upload is @1 or @last"),
+        Ok(n) => Some(n - 1),
+        Err(_) => None
+      }
+    };
+    let position = match index {
+      Some(i) if i < records.len() => Some(i),
+      Some(_) => None,
+      None => records.iter().position(|r| r.alias.as_ref().map(|a| a == reference).unwrap_or(false))
+    };
+    match position {
+      Some(i) => Ok(records.swap_remove(i)),
+      None => bail!("there is no upload called @{}", reference)
+    }
+  }
+
+  fn delete(&self, url: &str) -> Result<String> {
+    let (bin, id, _) = self.locate(url)?;
+    if !bin.features().contains(&BinFeature::Deletable) {
+      bail!("{} does not support deleting pastes", bin.name());
+    }
+    bin.delete(&id)?;
+    info!("deleted {}", url);
+    Ok(Default::default())
+  }
+
+  fn download(&self, url: &str, names: Option<&[&str]>) -> Result<String> {
+    if names.is_some() && self.cli_options.range.is_some() {
+      bail!("cannot specify file names with --range");
+    }
+    let (bin, id, key) = self.locate(url)?;
     if let Some(ref output_mode) = self.cli_options.url_output {
       let urls = match *output_mode {
         UrlOutputMode::Html => bin.create_html_url(&id),
@@ -585,6 +680,13 @@ impl<'a> Bins<'a> {
       DownloadInfo::empty()
     };
     let download = bin.download(&id, &download_info)?;
+    let download = match key {
+      Some(ref k) => match download {
+        Paste::Single(f) => Paste::Single(k.decrypt(f)?),
+        Paste::Multiple(fs) => Paste::Multiple(fs.into_iter().map(|f| k.decrypt(f)).collect::<Result<_>>()?)
+      },
+      None => download
+    };
     if let Some(ref path_str) = self.cli_options.output {
       let path = Path::new(path_str);
       if !path.exists() {
@@ -619,7 +721,7 @@ impl<'a> Bins<'a> {
           .open(download_path)
           .chain_err(|| format!("could not open {}", name))?;
         file
-          .write_all(download.content.as_bytes())
+          .write_all(&download.content)
           .chain_err(|| format!("could not write to {}", name))?;
       }
       return Ok(Default::default());
@@ -629,22 +731,41 @@ impl<'a> Bins<'a> {
       Ok(j)
     } else {
       let output = match download {
-        Paste::Single(f) => f.content,
-        Paste::Multiple(fs) =>
-          fs.iter()
-            .map(|f| format!("==> {} <==\n\n{}", f.name.name(), f.content))
-            .collect::<Vec<_>>()
-            .join("\n")
+        Paste::Single(f) => into_text(f)?,
+        Paste::Multiple(fs) => {
+          let mut outputs = Vec::with_capacity(fs.len());
+          for f in fs {
+            let name = f.name.name();
+            outputs.push(format!("==> {} <==\n\n{}", name, into_text(f)?));
+          }
+          outputs.join("\n")
+        }
       };
       Ok(output)
     }
   }
 }
 
+/// Splits an input of the form `file.rs:lang=rust` into the file and its language, unless the whole input is a file.
+fn split_language(input: &str) -> (&str, Option<&str>) {
+  if Path::new(input).exists() {
+    return (input, None);
+  }
+  match input.rfind(":lang=") {
+    Some(i) => (&input[..i], Some(&input[i + ":lang=".len()..])),
+    None => (input, None)
+  }
+}
+
+fn into_text(file: DownloadedFile) -> Result<String> {
+  let name = file.name.name();
+  String::from_utf8(file.content).chain_err(|| format!("{} is a binary file. use --output to save it", name))
+}
+
 fn get_stdin() -> Result<UploadFile> {
-  let mut content = String::new();
+  let mut content = Vec::new();
   let mut stdin = std::io::stdin();
-  stdin.read_to_string(&mut content)?;
+  stdin.read_to_end(&mut content)?;
   Ok(UploadFile::new("stdin".to_owned(), content))
 }
 
@@ -663,102 +784,21 @@ fn error_parents(error: &Error) -> Vec<&Error> {
   parents
 }
 
-fn get_config() -> Result<Config> {
-  let mut f = match find_config_path() {
-    Some(p) => File::open(p)?,
-    None => create_config_file()?
-  };
-  let mut content = String::new();
-  f.read_to_string(&mut content)?;
-  toml::from_str(&content).chain_err(|| "could not parse configuration file")
-}
-
-fn create_xdg_config_file() -> Result<File> {
-  if let Ok(xdg_dir) = std::env::var("XDG_CONFIG_DIR") {
-    let xdg_path = Path::new(&xdg_dir);
-    let xdg_config_path = xdg_path.join("bins.cfg");
-    if xdg_path.exists() && xdg_path.is_dir() && !xdg_config_path.exists() {
-      return OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(xdg_config_path)
-        .chain_err(|| "could not open/create XDG_CONFIG_DIR config file");
-    }
-  }
-  Err(ErrorKind::Config.into())
-}
-
-fn create_home_config_file() -> Result<File> {
-  if let Ok(home_dir) = std::env::var("HOME") {
-    let home = Path::new(&home_dir);
-    let home_folder = home.join(".config");
-    let home_folder_config = home_folder.join("bins.cfg");
-    if home_folder.exists() && home_folder.is_dir() && !home_folder_config.exists() {
-      return OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(home_folder_config)
-        .chain_err(|| "could not open/create HOME/.config config file");
-    }
-    let home_config = Path::new(&home_dir).join(".bins.cfg");
-    if home.exists() && home.is_dir() && !home_config.exists() {
-      return OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(home_config)
-        .chain_err(|| "could not open/create HOME config file");
-    }
-  }
-  Err(ErrorKind::Config.into())
-}
-
-fn create_config_file() -> Result<File> {
-  let mut f = match create_xdg_config_file() {
-    Ok(f) => f,
-    Err(_) => match create_home_config_file() {
-      Ok(hf) => hf,
-      Err(_) => return Err(ErrorKind::Config.into())
-    }
-  };
-  let mut default_config = String::new();
-  GzDecoder::new(config::DEFAULT_CONFIG_GZIP)?
-    .read_to_string(&mut default_config)?;
-  f.write_all(default_config.as_bytes())?;
-  f.seek(SeekFrom::Start(0))?;
-  Ok(f)
-}
-
-fn find_config_path() -> Option<PathBuf> {
-  if let Ok(xdg_dir) = std::env::var("XDG_CONFIG_DIR") {
-    let xdg_config_path = Path::new(&xdg_dir).join("bins.cfg");
-    if xdg_config_path.exists() {
-      return Some(xdg_config_path.to_owned());
-    }
-  }
-  if let Ok(home_dir) = std::env::var("HOME") {
-    let home_config_folder = Path::new(&home_dir).join(".config").join("bins.cfg");
-    if home_config_folder.exists() {
-      return Some(home_config_folder.to_owned());
-    }
-    let home_config = Path::new(&home_dir).join(".bins.cfg");
-    if home_config.exists() {
-      return Some(home_config.to_owned());
-    }
-  }
-  None
+/// Adds the key to every URL in the output.
+fn add_key(key: &Key, output: &str) -> String {
+  output.lines().map(|l| key.add_to_str(l)).collect::<Vec<_>>().join("\n")
 }
 
-#[cfg(feature = "openssl")]
-pub fn new_client() -> Client {
-  use hyper_openssl::OpensslClient;
-  Client::with_connector(HttpsConnector::new(OpensslClient::new().unwrap()))
+/// Checks if an input refers to a previous upload, like `@last`, rather than a file.
+fn is_reference(input: &str) -> bool {
+  input.starts_with('@') && input.len() > 1 && !Path::new(input).exists()
 }
 
-#[cfg(feature = "rustls")]
-pub fn new_client() -> Client {
-  use hyper_rustls::TlsClient;
-  Client::with_connector(HttpsConnector::new(TlsClient::new()))
+fn find_history_path() -> Option<PathBuf> {
+  let config_path = option!(find_config_path());
+  let hidden = config_path.file_name()
+    .and_then(|n| n.to_str())
+    .map(|n| n.starts_with('.'))
+    .unwrap_or(false);
+  Some(config_path.with_file_name(if hidden { ".bins_history.jsonl" } else { "bins_history.jsonl" }))
 }
@@ -0,0 +1,400 @@
+//! An in-memory bin for testing code that uses bins, without a network.
+//!
+//! `MockBin` implements every bin trait. Pastes are kept in memory, every call is recorded so tests can check what was
+//! uploaded, and failures can be injected to test error handling.
+
+use error::*;
+use files::*;
+use transport::{HttpClient, Replay};
+use super::*;
+
+use url::Url;
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// The operations a `MockBin` can be told to fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockOperation {
+  Upload,
+  Update,
+  Download,
+  Delete
+}
+
+/// A call made to a `MockBin`.
+#[derive(Debug, Clone)]
+pub enum MockCall {
+  Upload {
+    id: String,
+    files: Vec<UploadFile>,
+    index: bool
+  },
+  Update {
+    id: String,
+    files: Vec<UploadFile>
+  },
+  Delete {
+    id: String
+  }
+}
+
+pub struct MockBin {
+  name: String,
+  host: String,
+  features: Vec<BinFeature>,
+  client: HttpClient,
+  state: Mutex<MockState>
+}
+
+#[derive(Default)]
+struct MockState {
+  pastes: BTreeMap<String, Vec<DownloadedFile>>,
+  calls: Vec<MockCall>,
+  next_id: usize,
+  failures: Vec<(MockOperation, String, bool)>
+}
+
+impl MockBin {
+  /// Creates an empty bin with the given name and features. Its URLs use the host `<name>.mock.invalid`.
+  pub fn new(name: &str, features: Vec<BinFeature>) -> MockBin {
+    MockBin {
+      name: name.to_owned(),
+      host: format!("{}.mock.invalid", name),
+      features: features,
+      // downloads never reach the client, but if they did, this fails instead of using the network
+      client: HttpClient::new(Replay::new(Vec::new())),
+      state: Mutex::new(MockState::default())
+    }
+  }
+
+  /// Makes the next call of `operation` fail with `message`.
+  pub fn fail_next(&self, operation: MockOperation, message: &str) {
+    self.state().failures.push((operation, message.to_owned(), false));
+  }
+
+  /// Makes every call of `operation` fail with `message`, until `clear_failures` is called.
+  pub fn fail_always(&self, operation: MockOperation, message: &str) {
+    self.state().failures.push((operation, message.to_owned(), true));
+  }
+
+  pub fn clear_failures(&self) {
+    self.state().failures.clear();
+  }
+
+  /// Every call made to this bin, in order. Calls that failed because of an injected failure are not included.
+  pub fn calls(&self) -> Vec<MockCall> {
+    self.state().calls.clone()
+  }
+
+  /// The files in the paste with the given ID, if it exists.
+  pub fn paste(&self, id: &str) -> Option<Vec<DownloadedFile>> {
+    self.state().pastes.get(id).cloned()
+  }
+
+  /// The IDs of every paste that has not been deleted.
+  pub fn ids(&self) -> Vec<String> {
+    self.state().pastes.keys().cloned().collect()
+  }
+
+  fn state(&self) -> MutexGuard<MockState> {
+    // a panicking test should not hide the state from the next one
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  fn check_failure(&self, state: &mut MockState, operation: MockOperation) -> Result<()> {
+    let position = match state.failures.iter().position(|&(op, _, _)| op == operation) {
+      Some(p) => p,
+      None => return Ok(())
+    };
+    let message = if state.failures[position].2 {
+      state.failures[position].1.clone()
+    } else {
+      state.failures.remove(position).1
+    };
+    Err(ErrorKind::BinError(message).into())
+  }
+
+  fn check_files(&self, contents: &[UploadFile]) -> Result<Vec<DownloadedFile>> {
+    if contents.len() > 1 && !self.features.contains(&BinFeature::MultiFile) {
+      bail!("{} does not support multiple files", self.name);
+    }
+    if !self.features.contains(&BinFeature::BinaryFiles) {
+      if let Some(f) = contents.iter().find(|f| f.is_binary()) {
+        bail!("{} is not valid utf-8", f.name);
+      }
+    }
+    Ok(contents.iter()
+      .map(|f| DownloadedFile::new(PasteFileName::Explicit(f.name.clone()), f.content.clone()))
+      .collect())
+  }
+
+  fn id_from_url(&self, url: &str, prefix: Option<&str>) -> Option<String> {
+    let url = match Url::parse(url) {
+      Ok(u) => u,
+      Err(_) => return None
+    };
+    if ::bins::url_host(&url).as_ref() != Some(&self.host) {
+      return None;
+    }
+    let mut segments = match url.path_segments() {
+      Some(s) => s,
+      None => return None
+    };
+    if let Some(p) = prefix {
+      if segments.next() != Some(p) {
+        return None;
+      }
+    }
+    segments.next().and_then(|x| if x.is_empty() { None } else { Some(x.to_owned()) })
+  }
+}
+
+impl Bin for MockBin {
+  fn name(&self) -> &str {
+    &self.name
+  }
+
+  fn html_host(&self) -> &str {
+    &self.host
+  }
+
+  fn raw_host(&self) -> &str {
+    &self.host
+  }
+}
+
+impl ManagesUrls for MockBin {}
+
+impl CreatesUrls for MockBin {}
+
+impl FormatsUrls for MockBin {}
+
+impl FormatsHtmlUrls for MockBin {
+  fn format_html_url(&self, id: &str) -> Option<String> {
+    Some(format!("https://{}/{}", self.host, id))
+  }
+}
+
+impl FormatsRawUrls for MockBin {
+  fn format_raw_url(&self, id: &str) -> Option<String> {
+    Some(format!("https://{}/raw/{}", self.host, id))
+  }
+}
+
+impl CreatesHtmlUrls for MockBin {
+  fn create_html_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let state = self.state();
+    let files = match state.pastes.get(id) {
+      Some(f) => f,
+      None => bail!("{} has no paste {}", self.name, id)
+    };
+    let url = self.format_html_url(id).unwrap();
+    Ok(files.iter().map(|f| PasteUrl::html(Some(f.name.clone()), url.clone())).collect())
+  }
+
+  fn id_from_html_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url, None)
+  }
+}
+
+impl CreatesRawUrls for MockBin {
+  fn create_raw_url(&self, id: &str) -> Result<Vec<PasteUrl>> {
+    let mut state = self.state();
+    self.check_failure(&mut state, MockOperation::Download)?;
+    let files = match state.pastes.get(id) {
+      Some(f) => f,
+      None => bail!("{} has no paste {}", self.name, id)
+    };
+    let url = self.format_raw_url(id).unwrap();
+    // the files are handed over already downloaded, so downloading never uses the client
+    Ok(files.iter().map(|f| PasteUrl::Downloaded(url.clone(), f.clone())).collect())
+  }
+
+  fn id_from_raw_url(&self, url: &str) -> Option<String> {
+    self.id_from_url(url, Some("raw"))
+  }
+}
+
+impl HasFeatures for MockBin {
+  fn features(&self) -> Vec<BinFeature> {
+    self.features.clone()
+  }
+}
+
+impl Uploads for MockBin {
+  fn upload(&self, contents: &[UploadFile], index: bool) -> Result<Vec<PasteUrl>> {
+    let mut state = self.state();
+    self.check_failure(&mut state, MockOperation::Upload)?;
+    let files = self.check_files(contents)?;
+    state.next_id += 1;
+    let id = format!("{}{}", self.name, state.next_id);
+    state.pastes.insert(id.clone(), files);
+    state.calls.push(MockCall::Upload {
+      id: id.clone(),
+      files: contents.to_vec(),
+      index: index
+    });
+    let url = PasteUrl::html(None, self.format_html_url(&id).unwrap());
+    if self.features.contains(&BinFeature::Deletable) {
+      Ok(vec![url.with_deletion_key(format!("{}-key", id))])
+    } else {
+      Ok(vec![url])
+    }
+  }
+}
+
+impl Updates for MockBin {
+  fn update(&self, id: &str, contents: &[UploadFile]) -> Result<Vec<PasteUrl>> {
+    if !self.features.contains(&BinFeature::Updatable) {
+      bail!("{} does not support updating pastes", self.name);
+    }
+    let mut state = self.state();
+    self.check_failure(&mut state, MockOperation::Update)?;
+    let files = self.check_files(contents)?;
+    {
+      let existing = match state.pastes.get_mut(id) {
+        Some(e) => e,
+        None => bail!("{} has no paste {}", self.name, id)
+      };
+      for file in files {
+        existing.retain(|f| f.name.name() != file.name.name());
+        existing.push(file);
+      }
+    }
+    state.calls.push(MockCall::Update {
+      id: id.to_owned(),
+      files: contents.to_vec()
+    });
+    Ok(vec![PasteUrl::html(None, self.format_html_url(id).unwrap())])
+  }
+}
+
+impl Deletes for MockBin {
+  fn delete(&self, id: &str) -> Result<()> {
+    if !self.features.contains(&BinFeature::Deletable) {
+      bail!("{} does not support deleting pastes", self.name);
+    }
+    let mut state = self.state();
+    self.check_failure(&mut state, MockOperation::Delete)?;
+    if state.pastes.remove(id).is_none() {
+      bail!("{} has no paste {}", self.name, id);
+    }
+    state.calls.push(MockCall::Delete {
+      id: id.to_owned()
+    });
+    Ok(())
+  }
+}
+
+impl HasClient for MockBin {
+  fn client(&self) -> &HttpClient {
+    &self.client
+  }
+
+  fn set_client(&mut self, client: HttpClient) {
+    self.client = client;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn files(names: &[&str]) -> Vec<UploadFile> {
+    names.iter().map(|n| UploadFile::new(n.to_string(), format!("contents of {}", n).into_bytes())).collect()
+  }
+
+  fn mock() -> MockBin {
+    MockBin::new("test", vec![BinFeature::MultiFile, BinFeature::Updatable, BinFeature::Deletable])
+  }
+
+  #[test]
+  fn fail_next_fails_once() {
+    let bin = mock();
+    bin.fail_next(MockOperation::Upload, "down for maintenance");
+    let err = bin.upload(&files(&["a.txt"]), false).unwrap_err();
+    assert_eq!(err.to_string(), "down for maintenance");
+    assert!(bin.upload(&files(&["a.txt"]), false).is_ok());
+    assert_eq!(bin.ids(), vec!["test1"]);
+  }
+
+  #[test]
+  fn fail_next_only_fails_its_operation() {
+    let bin = mock();
+    bin.fail_next(MockOperation::Delete, "no");
+    let urls = bin.upload(&files(&["a.txt"]), false).unwrap();
+    let id = bin.id_from_html_url(urls[0].url()).unwrap();
+    assert!(bin.delete(&id).is_err());
+    assert!(bin.delete(&id).is_ok());
+  }
+
+  #[test]
+  fn fail_always_fails_until_cleared() {
+    let bin = mock();
+    bin.fail_always(MockOperation::Upload, "always");
+    for _ in 0..3 {
+      assert_eq!(bin.upload(&files(&["a.txt"]), false).unwrap_err().to_string(), "always");
+    }
+    bin.clear_failures();
+    assert!(bin.upload(&files(&["a.txt"]), false).is_ok());
+  }
+
+  #[test]
+  fn records_calls() {
+    let bin = mock();
+    bin.upload(&files(&["a.txt", "b.txt"]), true).unwrap();
+    bin.update("test1", &files(&["b.txt"])).unwrap();
+    bin.fail_next(MockOperation::Delete, "no");
+    assert!(bin.delete("test1").is_err());
+    bin.delete("test1").unwrap();
+    let calls = bin.calls();
+    assert_eq!(calls.len(), 3);
+    match calls[0] {
+      MockCall::Upload { ref id, ref files, index } => {
+        assert_eq!(id, "test1");
+        assert_eq!(files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>(), vec!["a.txt", "b.txt"]);
+        assert!(index);
+      },
+      ref c => panic!("expected an upload, got {:?}", c)
+    }
+    match calls[1] {
+      MockCall::Update { ref id, ref files } => {
+        assert_eq!(id, "test1");
+        assert_eq!(files.len(), 1);
+      },
+      ref c => panic!("expected an update, got {:?}", c)
+    }
+    match calls[2] {
+      MockCall::Delete { ref id } => assert_eq!(id, "test1"),
+      ref c => panic!("expected a delete, got {:?}", c)
+    }
+  }
+
+  #[test]
+  fn upload_then_download() {
+    let bin = mock();
+    let uploaded = files(&["a.txt", "b.txt"]);
+    let urls = bin.upload(&uploaded, false).unwrap();
+    let id = bin.id_from_html_url(urls[0].url()).unwrap();
+    let raw_url = bin.format_raw_url(&id).unwrap();
+    assert_eq!(bin.id_from_raw_url(&raw_url), Some(id.clone()));
+    let downloaded = match bin.download(&id, &DownloadInfo::default()).unwrap() {
+      Paste::Multiple(fs) => fs,
+      Paste::Single(f) => vec![f]
+    };
+    assert_eq!(downloaded.len(), uploaded.len());
+    for (down, up) in downloaded.iter().zip(uploaded.iter()) {
+      assert_eq!(down.name.name(), up.name);
+      assert_eq!(down.content, up.content);
+    }
+  }
+
+  #[test]
+  fn ignores_other_hosts() {
+    let bin = mock();
+    assert_eq!(bin.id_from_html_url("https://test.mock.invalid/test1"), Some("test1".to_owned()));
+    assert_eq!(bin.id_from_html_url("https://other.mock.invalid/test1"), None);
+    assert_eq!(bin.id_from_html_url("https://test.mock.invalid:8080/test1"), None);
+  }
+}
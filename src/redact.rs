@@ -0,0 +1,109 @@
+use regex::{self, NoExpand, Regex};
+
+use Result;
+use error::*;
+use files::UploadFile;
+use scan::BUILTIN_PATTERNS;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+
+/// Patterns for personal or identifying information, and the placeholders they are replaced with.
+const PATTERNS: &'static [(&'static str, &'static str, &'static str)] = &[
+  ("email address", r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b", "[redacted email]"),
+  ("IPv4 address", r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b", "[redacted ip]"),
+  ("IPv6 address", r"\b(?:[0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}\b", "[redacted ip]"),
+  ("home directory", r"(?:/home|/Users)/[^/\s]+|[A-Za-z]:\\Users\\[^\\\s]+", "~")
+];
+
+pub struct Redactor {
+  rules: Vec<(String, Regex, String)>
+}
+
+impl Redactor {
+  pub fn new(custom_patterns: &[String]) -> Result<Redactor> {
+    let mut rules = Vec::new();
+    for &(name, pattern) in BUILTIN_PATTERNS {
+      let regex = Regex::new(pattern).chain_err(|| format!("invalid built-in pattern for {}", name))?;
+      rules.push((name.to_owned(), regex, "[redacted secret]".to_owned()));
+    }
+    for &(name, pattern, placeholder) in PATTERNS {
+      let regex = Regex::new(pattern).chain_err(|| format!("invalid built-in pattern for {}", name))?;
+      rules.push((name.to_owned(), regex, placeholder.to_owned()));
+    }
+    // the home directory and hostname are checked literally, since they may not look like either
+    if let Ok(home) = env::var("HOME") {
+      if home.len() > 1 {
+        rules.push(("home directory".to_owned(), literal(&home, false)?, "~".to_owned()));
+      }
+    }
+    if let Some(hostname) = hostname() {
+      rules.push(("hostname".to_owned(), literal(&hostname, true)?, "[redacted host]".to_owned()));
+    }
+    for pattern in custom_patterns {
+      let regex = Regex::new(pattern).chain_err(|| format!("the redaction pattern \"{}\" is invalid", pattern))?;
+      rules.push((format!("match for \"{}\"", pattern), regex, "[redacted]".to_owned()));
+    }
+    Ok(Redactor {
+      rules: rules
+    })
+  }
+
+  /// Redacts the content of the file in place, returning how many of each kind of match were replaced.
+  pub fn redact(&self, file: &mut UploadFile) -> Vec<(String, usize)> {
+    let mut text = match String::from_utf8(file.content.clone()) {
+      Ok(t) => t,
+      Err(_) => {
+        warn!("not redacting {}, since it is a binary file", file.name);
+        return Vec::new();
+      }
+    };
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for &(ref name, ref regex, ref placeholder) in &self.rules {
+      let count = regex.find_iter(&text).count();
+      if count == 0 {
+        continue;
+      }
+      let replaced = regex.replace_all(&text, NoExpand(placeholder.as_str())).into_owned();
+      text = replaced;
+      match counts.iter().position(|&(ref n, _)| n == name) {
+        Some(i) => counts[i].1 += count,
+        None => counts.push((name.clone(), count))
+      }
+    }
+    file.content = text.into_bytes();
+    counts
+  }
+}
+
+fn literal(s: &str, whole_word: bool) -> Result<Regex> {
+  let pattern = if whole_word {
+    format!(r"\b{}\b", regex::escape(s))
+  } else {
+    regex::escape(s)
+  };
+  Regex::new(&pattern).chain_err(|| format!("could not create a pattern for {}", s))
+}
+
+fn hostname() -> Option<String> {
+  let hostname = match env::var("HOSTNAME") {
+    Ok(h) => h,
+    Err(_) => {
+      let mut content = String::new();
+      match File::open("/etc/hostname") {
+        Ok(mut f) => if f.read_to_string(&mut content).is_err() {
+          return None;
+        },
+        Err(_) => return None
+      }
+      content
+    }
+  };
+  let hostname = hostname.trim();
+  if hostname.is_empty() || hostname == "localhost" {
+    None
+  } else {
+    Some(hostname.to_owned())
+  }
+}
@@ -0,0 +1,124 @@
+use regex::Regex;
+
+use Result;
+use error::*;
+use files::UploadFile;
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Patterns for credentials that are recognisable by their shape alone.
+pub const BUILTIN_PATTERNS: &'static [(&'static str, &'static str)] = &[
+  ("AWS access key ID", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+  ("AWS secret access key", r#"(?i)aws_?secret_?access_?key["']?\s*[:=]\s*["']?[0-9a-zA-Z/+]{40}"#),
+  ("GitHub token", r"\bgh[pousr]_[0-9A-Za-z]{36,}\b"),
+  ("Slack token", r"\bxox[abposr]-[0-9A-Za-z-]{10,}"),
+  ("private key", r"-----BEGIN (?:[A-Z0-9]+ )*PRIVATE KEY(?: BLOCK)?-----"),
+  ("JSON web token", r"\beyJ[0-9A-Za-z_-]+\.eyJ[0-9A-Za-z_-]+\.[0-9A-Za-z_-]+")
+];
+
+// strings at least this long made of these characters are checked for entropy
+const ENTROPY_CANDIDATE: &'static str = r"[0-9A-Za-z+/=_-]{24,}";
+// bits of entropy per character above which a candidate is reported
+const ENTROPY_THRESHOLD: f64 = 4.0;
+
+#[derive(Debug)]
+pub struct Finding {
+  pub file: String,
+  pub line: usize,
+  pub kind: String,
+  pub preview: String
+}
+
+impl Display for Finding {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    write!(f, "{}:{}: {} ({})", self.file, self.line, self.kind, self.preview)
+  }
+}
+
+pub struct Scanner {
+  rules: Vec<(String, Regex)>,
+  entropy: Option<Regex>
+}
+
+impl Scanner {
+  pub fn new(custom_patterns: &[String], check_entropy: bool) -> Result<Scanner> {
+    let mut rules = Vec::with_capacity(BUILTIN_PATTERNS.len() + custom_patterns.len());
+    for &(name, pattern) in BUILTIN_PATTERNS {
+      rules.push((name.to_owned(), Regex::new(pattern).chain_err(|| format!("invalid built-in pattern for {}", name))?));
+    }
+    for pattern in custom_patterns {
+      let regex = Regex::new(pattern).chain_err(|| format!("the secret pattern \"{}\" is invalid", pattern))?;
+      rules.push((format!("match for \"{}\"", pattern), regex));
+    }
+    let entropy = if check_entropy {
+      Some(Regex::new(ENTROPY_CANDIDATE).chain_err(|| "invalid entropy pattern")?)
+    } else {
+      None
+    };
+    Ok(Scanner {
+      rules: rules,
+      entropy: entropy
+    })
+  }
+
+  pub fn scan(&self, file: &UploadFile) -> Vec<Finding> {
+    let content = String::from_utf8_lossy(&file.content);
+    let mut findings = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+      for &(ref name, ref regex) in &self.rules {
+        for m in regex.find_iter(line) {
+          findings.push(Finding {
+            file: file.name.clone(),
+            line: i + 1,
+            kind: name.clone(),
+            preview: preview(m.as_str())
+          });
+        }
+      }
+      if let Some(ref regex) = self.entropy {
+        for m in regex.find_iter(line) {
+          let candidate = m.as_str();
+          if looks_random(candidate) && findings.iter().all(|f| f.line != i + 1 || f.file != file.name) {
+            findings.push(Finding {
+              file: file.name.clone(),
+              line: i + 1,
+              kind: "high-entropy string".to_owned(),
+              preview: preview(candidate)
+            });
+          }
+        }
+      }
+    }
+    findings
+  }
+}
+
+/// Shows only the start of a secret, so findings can be printed without leaking it.
+fn preview(secret: &str) -> String {
+  let start: String = secret.chars().take(4).collect();
+  format!("{}…", start)
+}
+
+fn looks_random(s: &str) -> bool {
+  let has_lower = s.chars().any(|c| c.is_lowercase());
+  let has_upper = s.chars().any(|c| c.is_uppercase());
+  let has_digit = s.chars().any(|c| c.is_digit(10));
+  has_lower && has_upper && has_digit && shannon_entropy(s) > ENTROPY_THRESHOLD
+}
+
+fn shannon_entropy(s: &str) -> f64 {
+  let mut counts = HashMap::new();
+  let mut len = 0;
+  for c in s.chars() {
+    *counts.entry(c).or_insert(0) += 1;
+    len += 1;
+  }
+  let len = len as f64;
+  counts.values()
+    .map(|&count| {
+      let p = count as f64 / len;
+      -p * p.log2()
+    })
+    .sum()
+}
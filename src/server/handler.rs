@@ -0,0 +1,421 @@
+use lib::error::*;
+use lib::files::{IndexedFile, UploadFile};
+use lib::units::parse_duration;
+use storage::{Storage, StoredPaste};
+
+use hyper::header::{Authorization, ContentType};
+use hyper::method::Method;
+use hyper::mime::{Mime, TopLevel, SubLevel};
+use hyper::server::{Handler, Request, Response};
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use ring::constant_time::verify_slices_are_equal;
+use serde::Serialize;
+use serde_json;
+use url::percent_encoding::{percent_decode, utf8_percent_encode, PATH_SEGMENT_ENCODE_SET};
+
+use std::io::Read;
+
+/// Serves the hastebin document API and the multi-file JSON API from a `Storage`.
+///
+/// The hastebin API is `POST /documents`, `GET /documents/:id`, `GET /raw/:id` and `GET /:id`, so the hastebin bin can be
+/// pointed at this server. The JSON API is `POST /api/pastes`, `GET /api/pastes/:id` and `DELETE /api/pastes/:id`, and
+/// the files in a paste can be downloaded from `GET /raw/:id/:name`.
+pub struct PasteServer {
+  storage: Storage,
+  url: String,
+  max_size: u64
+}
+
+impl PasteServer {
+  /// Creates a server that stores pastes in `storage`, refuses pastes bigger than `max_size` bytes and gives out URLs
+  /// starting with `url`.
+  pub fn new(storage: Storage, url: &str, max_size: u64) -> PasteServer {
+    PasteServer {
+      storage: storage,
+      url: url.trim_right_matches('/').to_owned(),
+      max_size: max_size
+    }
+  }
+
+  fn route(&self, req: &mut Request) -> Outcome {
+    let path = match req.uri {
+      RequestUri::AbsolutePath(ref p) => p.split('?').next().unwrap_or("").to_owned(),
+      _ => return Err(Reply::error(StatusCode::BadRequest, "invalid request path"))
+    };
+    let segments: Vec<String> = path.split('/')
+      .filter(|x| !x.is_empty())
+      .map(|x| percent_decode(x.as_bytes()).decode_utf8_lossy().into_owned())
+      .collect();
+    let s: Vec<&str> = segments.iter().map(|x| x.as_str()).collect();
+    let method = req.method.clone();
+    match (method, s.len()) {
+      (Method::Post, 1) if s[0] == "documents" => self.create_document(req),
+      (Method::Get, 2) if s[0] == "documents" => self.get_document(s[1]),
+      (Method::Get, 2) if s[0] == "raw" => self.raw(s[1], None),
+      (Method::Get, 3) if s[0] == "raw" => self.raw(s[1], Some(s[2])),
+      (Method::Post, 2) if s[0] == "api" && s[1] == "pastes" => self.create_paste(req),
+      (Method::Get, 3) if s[0] == "api" && s[1] == "pastes" => self.get_paste(s[2]),
+      (Method::Delete, 3) if s[0] == "api" && s[1] == "pastes" => self.delete_paste(req, s[2]),
+      // the hastebin bin's html urls
+      (Method::Get, 1) => self.raw(s[0], None),
+      _ => Err(Reply::error(StatusCode::NotFound, "not found"))
+    }
+  }
+
+  fn create_document(&self, req: &mut Request) -> Outcome {
+    let body = self.read_body(req, self.max_size)?;
+    let (id, _) = self.storage.create(vec![UploadFile::new("document".to_owned(), body)], None)?;
+    info!("created document {}", id);
+    Ok(Reply::json(StatusCode::Ok, &json!({ "key": id })))
+  }
+
+  fn get_document(&self, id: &str) -> Outcome {
+    let paste = self.find(id)?;
+    let file = self.single_file(paste, None)?;
+    Ok(Reply::json(StatusCode::Ok, &json!({
+      "key": id,
+      "data": String::from_utf8_lossy(&file.content)
+    })))
+  }
+
+  fn raw(&self, id: &str, name: Option<&str>) -> Outcome {
+    let paste = self.find(id)?;
+    let file = self.single_file(paste, name)?;
+    Ok(Reply::file(file.content))
+  }
+
+  fn create_paste(&self, req: &mut Request) -> Outcome {
+    // files are escaped or base64-encoded in json, so the body can be bigger than the files it holds
+    let body = self.read_body(req, self.max_size.saturating_mul(2))?;
+    let request: CreatePaste = serde_json::from_slice(&body)
+      .map_err(|e| Reply::error(StatusCode::BadRequest, &format!("invalid paste: {}", e)))?;
+    if request.files.is_empty() {
+      return Err(Reply::error(StatusCode::BadRequest, "a paste needs at least one file"));
+    }
+    if request.files.iter().enumerate().any(|(i, f)| request.files[..i].iter().any(|o| o.name == f.name)) {
+      return Err(Reply::error(StatusCode::BadRequest, "the files in a paste need different names"));
+    }
+    let size: u64 = request.files.iter().map(|f| f.content.len() as u64).sum();
+    if size > self.max_size {
+      return Err(too_large());
+    }
+    let expires_in = match request.expires {
+      Some(ref e) => Some(parse_duration(e).map_err(|e| Reply::error(StatusCode::BadRequest, &e.to_string()))?),
+      None => None
+    };
+    let (id, paste) = self.storage.create(request.files, expires_in)?;
+    info!("created paste {} with {} file(s)", id, paste.files.len());
+    let files = paste.files.iter()
+      .map(|f| IndexedFile::new(f.name.clone(), self.raw_url(&id, &f.name)))
+      .collect();
+    Ok(Reply::json(StatusCode::Created, &CreatedPaste {
+      id: id,
+      deletion_key: paste.deletion_key,
+      expires: paste.expires,
+      files: files
+    }))
+  }
+
+  fn get_paste(&self, id: &str) -> Outcome {
+    let paste = self.find(id)?;
+    Ok(Reply::json(StatusCode::Ok, &json!({
+      "id": id,
+      "created": paste.created,
+      "expires": paste.expires,
+      "files": paste.files
+    })))
+  }
+
+  fn delete_paste(&self, req: &mut Request, id: &str) -> Outcome {
+    let paste = self.find(id)?;
+    // compared in constant time, so the key cannot be guessed a byte at a time from how long the check takes
+    let expected = format!("Key {}", paste.deletion_key);
+    let authorized = req.headers.get::<Authorization<String>>()
+      .map(|a| verify_slices_are_equal(a.0.as_bytes(), expected.as_bytes()).is_ok())
+      .unwrap_or(false);
+    if !authorized {
+      return Err(Reply::error(StatusCode::Forbidden, "the deletion key is missing or wrong"));
+    }
+    self.storage.delete(id)?;
+    info!("deleted paste {}", id);
+    Ok(Reply::empty(StatusCode::NoContent))
+  }
+
+  fn find(&self, id: &str) -> ::std::result::Result<StoredPaste, Reply> {
+    // hastebin urls can end in an extension for highlighting
+    let id = id.split('.').next().unwrap_or(id);
+    match self.storage.get(id)? {
+      Some(p) => Ok(p),
+      None => Err(Reply::error(StatusCode::NotFound, "paste not found"))
+    }
+  }
+
+  fn single_file(&self, paste: StoredPaste, name: Option<&str>) -> ::std::result::Result<UploadFile, Reply> {
+    let mut files = paste.files;
+    match name {
+      Some(n) => match files.into_iter().find(|f| f.name == n) {
+        Some(f) => Ok(f),
+        None => Err(Reply::error(StatusCode::NotFound, "file not found"))
+      },
+      None if files.len() == 1 => Ok(files.remove(0)),
+      None => Err(Reply::error(StatusCode::BadRequest, "the paste has more than one file, so use /raw/:id/:name"))
+    }
+  }
+
+  fn read_body(&self, req: &mut Request, limit: u64) -> ::std::result::Result<Vec<u8>, Reply> {
+    let mut body = Vec::new();
+    req.by_ref().take(limit.saturating_add(1)).read_to_end(&mut body).map_err(Error::from)?;
+    if body.len() as u64 > limit {
+      return Err(too_large());
+    }
+    Ok(body)
+  }
+
+  fn raw_url(&self, id: &str, name: &str) -> String {
+    format!("{}/raw/{}/{}", self.url, id, utf8_percent_encode(name, PATH_SEGMENT_ENCODE_SET))
+  }
+}
+
+impl Handler for PasteServer {
+  fn handle(&self, mut req: Request, mut res: Response) {
+    let reply = match self.route(&mut req) {
+      Ok(r) | Err(r) => r
+    };
+    debug!("{} {} -> {}", req.method, req.uri, reply.status);
+    *res.status_mut() = reply.status;
+    if let Some(content_type) = reply.content_type {
+      res.headers_mut().set(content_type);
+    }
+    if let Err(e) = res.send(&reply.body) {
+      warn!("could not send response: {}", e);
+    }
+  }
+}
+
+type Outcome = ::std::result::Result<Reply, Reply>;
+
+struct Reply {
+  status: StatusCode,
+  content_type: Option<ContentType>,
+  body: Vec<u8>
+}
+
+impl Reply {
+  fn json<T: Serialize>(status: StatusCode, value: &T) -> Reply {
+    Reply {
+      status: status,
+      content_type: Some(ContentType::json()),
+      body: serde_json::to_vec(value).unwrap_or_default()
+    }
+  }
+
+  /// An error in the same shape as hastebin's, which the hastebin bin shows to the user.
+  fn error(status: StatusCode, message: &str) -> Reply {
+    Reply::json(status, &json!({ "message": message }))
+  }
+
+  fn file(content: Vec<u8>) -> Reply {
+    let content_type = if ::std::str::from_utf8(&content).is_ok() {
+      ContentType::plaintext()
+    } else {
+      ContentType(Mime(TopLevel::Application, SubLevel::Ext("octet-stream".to_owned()), vec![]))
+    };
+    Reply {
+      status: StatusCode::Ok,
+      content_type: Some(content_type),
+      body: content
+    }
+  }
+
+  fn empty(status: StatusCode) -> Reply {
+    Reply {
+      status: status,
+      content_type: None,
+      body: Vec::new()
+    }
+  }
+}
+
+impl From<Error> for Reply {
+  fn from(e: Error) -> Reply {
+    error!("{}", e);
+    for cause in e.iter().skip(1) {
+      error!("{}", cause);
+    }
+    Reply::error(StatusCode::InternalServerError, "the server could not handle the request")
+  }
+}
+
+fn too_large() -> Reply {
+  Reply::error(StatusCode::PayloadTooLarge, "the paste is bigger than this server allows")
+}
+
+#[derive(Deserialize)]
+struct CreatePaste {
+  files: Vec<UploadFile>,
+  /// How long the paste should last, such as "1h" or "7d". The server's own limit is used if this is longer or unset.
+  #[serde(default)]
+  expires: Option<String>
+}
+
+#[derive(Serialize)]
+struct CreatedPaste {
+  id: String,
+  deletion_key: String,
+  expires: i64,
+  files: Vec<IndexedFile>
+}
+
+#[cfg(test)]
+mod tests {
+  use super::PasteServer;
+  use storage::Storage;
+  use storage::tests::temp_directory;
+
+  use hyper::Client;
+  use hyper::header::Authorization;
+  use hyper::server::{Listening, Server};
+  use hyper::status::StatusCode;
+  use serde_json::{self, Value};
+
+  use std::fs;
+  use std::io::Read;
+  use std::path::PathBuf;
+
+  /// A server on a free local port, with pastes of up to 16 bytes.
+  struct TestServer {
+    listening: Listening,
+    directory: PathBuf,
+    url: String
+  }
+
+  impl TestServer {
+    fn start() -> TestServer {
+      let directory = temp_directory();
+      let storage = Storage::new(&directory, 60).unwrap();
+      let server = Server::http("127.0.0.1:0").unwrap();
+      let listening = server.handle(PasteServer::new(storage, "http://paste.example.com", 16)).unwrap();
+      let url = format!("http://{}", listening.socket);
+      TestServer {
+        listening: listening,
+        directory: directory,
+        url: url
+      }
+    }
+
+    fn url(&self, path: &str) -> String {
+      format!("{}{}", self.url, path)
+    }
+
+    fn stop(mut self) {
+      self.listening.close().unwrap();
+      fs::remove_dir_all(&self.directory).unwrap();
+    }
+  }
+
+  fn body(mut res: ::hyper::client::Response) -> (StatusCode, String) {
+    let mut body = String::new();
+    res.read_to_string(&mut body).unwrap();
+    (res.status, body)
+  }
+
+  fn create(client: &Client, server: &TestServer, paste: &Value) -> Value {
+    let req = serde_json::to_string(paste).unwrap();
+    let (status, res) = body(client.post(&server.url("/api/pastes")).body(&req[..]).send().unwrap());
+    assert_eq!(status, StatusCode::Created, "{}", res);
+    serde_json::from_str(&res).unwrap()
+  }
+
+  #[test]
+  fn json_api_round_trip() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let created = create(&client, &server, &json!({
+      "files": [
+        { "name": "one.txt", "content": "first" },
+        { "name": "two words.txt", "content": "second" }
+      ]
+    }));
+    let id = created["id"].as_str().unwrap();
+    assert_eq!(created["files"][0]["url"], format!("http://paste.example.com/raw/{}/one.txt", id));
+    assert_eq!(created["files"][1]["url"], format!("http://paste.example.com/raw/{}/two%20words.txt", id));
+
+    let (status, res) = body(client.get(&server.url(&format!("/api/pastes/{}", id))).send().unwrap());
+    assert_eq!(status, StatusCode::Ok);
+    let paste: Value = serde_json::from_str(&res).unwrap();
+    assert_eq!(paste["files"], json!([
+      { "name": "one.txt", "content": "first" },
+      { "name": "two words.txt", "content": "second" }
+    ]));
+
+    let (status, res) = body(client.get(&server.url(&format!("/raw/{}/two%20words.txt", id))).send().unwrap());
+    assert_eq!((status, res.as_str()), (StatusCode::Ok, "second"));
+    // a paste with more than one file has no single raw file
+    let (status, _) = body(client.get(&server.url(&format!("/raw/{}", id))).send().unwrap());
+    assert_eq!(status, StatusCode::BadRequest);
+
+    server.stop();
+  }
+
+  #[test]
+  fn json_api_rejects_invalid_pastes() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let pastes = [
+      json!({ "files": [] }),
+      json!({ "files": [{ "name": "a.txt", "content": "a" }, { "name": "a.txt", "content": "b" }] }),
+      json!({ "files": [{ "name": "a.txt", "content": "a" }], "expires": "soon" })
+    ];
+    for paste in &pastes {
+      let req = serde_json::to_string(paste).unwrap();
+      let (status, _) = body(client.post(&server.url("/api/pastes")).body(&req[..]).send().unwrap());
+      assert_eq!(status, StatusCode::BadRequest, "{}", req);
+    }
+    server.stop();
+  }
+
+  #[test]
+  fn delete_needs_the_key() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let created = create(&client, &server, &json!({ "files": [{ "name": "a.txt", "content": "a" }] }));
+    let path = server.url(&format!("/api/pastes/{}", created["id"].as_str().unwrap()));
+    let key = created["deletion_key"].as_str().unwrap();
+
+    let (status, _) = body(client.delete(&path).send().unwrap());
+    assert_eq!(status, StatusCode::Forbidden);
+    let (status, _) = body(client.delete(&path).header(Authorization("Key wrong".to_owned())).send().unwrap());
+    assert_eq!(status, StatusCode::Forbidden);
+    let (status, _) = body(client.get(&path).send().unwrap());
+    assert_eq!(status, StatusCode::Ok);
+
+    let (status, _) = body(client.delete(&path).header(Authorization(format!("Key {}", key))).send().unwrap());
+    assert_eq!(status, StatusCode::NoContent);
+    let (status, _) = body(client.get(&path).send().unwrap());
+    assert_eq!(status, StatusCode::NotFound);
+
+    server.stop();
+  }
+
+  #[test]
+  fn rejects_oversized_pastes() {
+    let server = TestServer::start();
+    let client = Client::new();
+    let big = "x".repeat(17);
+
+    let (status, _) = body(client.post(&server.url("/documents")).body(&big[..]).send().unwrap());
+    assert_eq!(status, StatusCode::PayloadTooLarge);
+    let (status, _) = body(client.post(&server.url("/documents")).body(&big[..16]).send().unwrap());
+    assert_eq!(status, StatusCode::Ok);
+
+    let req = serde_json::to_string(&json!({ "files": [{ "name": "a.txt", "content": big }] })).unwrap();
+    let (status, _) = body(client.post(&server.url("/api/pastes")).body(&req[..]).send().unwrap());
+    assert_eq!(status, StatusCode::PayloadTooLarge);
+
+    // nothing was stored for the pastes that were too big
+    assert_eq!(fs::read_dir(&server.directory).unwrap().count(), 1);
+
+    server.stop();
+  }
+}
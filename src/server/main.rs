@@ -0,0 +1,186 @@
+//! A self-hostable paste server that bins can upload to, using the hastebin bin or the multi-file JSON API.
+
+extern crate bins as lib;
+extern crate hyper;
+extern crate url;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate clap;
+#[macro_use]
+extern crate log;
+extern crate rand;
+extern crate ring;
+extern crate time;
+
+#[path = "../logger.rs"]
+mod logger;
+mod handler;
+mod storage;
+
+use handler::PasteServer;
+use storage::Storage;
+
+use lib::error::*;
+use lib::units::{parse_duration, parse_size};
+
+use clap::{App, Arg, ArgMatches};
+use hyper::server::Server;
+use log::LogLevel;
+
+use std::thread;
+use std::time::Duration;
+
+/// How often expired pastes are deleted, in seconds.
+const SWEEP_INTERVAL: u64 = 60;
+
+fn main() {
+  std::process::exit(inner());
+}
+
+fn inner() -> i32 {
+  let matches = create_app().get_matches();
+
+  let level = if matches.is_present("debug") {
+    LogLevel::Debug
+  } else {
+    LogLevel::Info
+  };
+  if let Err(e) = logger::Logger::new(level).init() {
+    println!("could not initialize logger: {}", e);
+    return 1;
+  }
+
+  match serve(&matches) {
+    Ok(_) => 0,
+    Err(e) => {
+      for (i, error) in e.iter().enumerate() {
+        if i == 0 {
+          error!("{}", error);
+        } else {
+          error!("caused by: {}", error);
+        }
+      }
+      1
+    }
+  }
+}
+
+fn serve(matches: &ArgMatches) -> Result<()> {
+  let address = matches.value_of("address").unwrap();
+  let max_size = parse_size(matches.value_of("max-size").unwrap()).chain_err(|| "invalid --max-size")?;
+  let expire = parse_duration(matches.value_of("expire").unwrap()).chain_err(|| "invalid --expire")?;
+  let url = match matches.value_of("url") {
+    Some(u) => u.to_owned(),
+    None => format!("http://{}", address)
+  };
+
+  let storage = Storage::new(matches.value_of("directory").unwrap(), expire)?;
+
+  let sweeper = storage.clone();
+  thread::spawn(move || {
+    loop {
+      match sweeper.sweep() {
+        Ok(0) => {},
+        Ok(n) => info!("deleted {} expired paste(s)", n),
+        Err(e) => warn!("could not delete expired pastes: {}", e)
+      }
+      thread::sleep(Duration::from_secs(SWEEP_INTERVAL));
+    }
+  });
+
+  let server = Server::http(address).chain_err(|| format!("could not listen on {}", address))?;
+  info!("listening on {}, giving out urls under {}", address, url);
+  // the listener waits for the server's threads when it is dropped, so this runs until the process is stopped
+  server.handle(PasteServer::new(storage, &url, max_size))?;
+  Ok(())
+}
+
+fn create_app() -> App<'static, 'static> {
+  App::new("bins-server")
+    .about("A paste server for bins, compatible with hastebin")
+    .author(crate_authors!())
+    .version(crate_version!())
+    .help_message("print help information and exit")
+    .arg(Arg::with_name("debug")
+      .long("debug")
+      .short("d")
+      .help("enable debug output"))
+    .arg(Arg::with_name("address")
+      .long("address")
+      .short("a")
+      .help("the address to listen on")
+      .takes_value(true)
+      .value_name("address")
+      .default_value("127.0.0.1:7777"))
+    .arg(Arg::with_name("directory")
+      .long("directory")
+      .short("D")
+      .help("the directory to store pastes in")
+      .takes_value(true)
+      .value_name("directory")
+      .default_value("pastes"))
+    .arg(Arg::with_name("url")
+      .long("url")
+      .short("u")
+      .help("the url the server is reached at, used in the urls it gives out (defaults to http:// and the address)")
+      .takes_value(true)
+      .value_name("url"))
+    .arg(Arg::with_name("max-size")
+      .long("max-size")
+      .short("s")
+      .help("the biggest paste to accept, such as 512KiB or 10MB")
+      .takes_value(true)
+      .value_name("size")
+      .default_value("1MiB"))
+    .arg(Arg::with_name("expire")
+      .long("expire")
+      .short("e")
+      .help("how long pastes are kept, such as 12h or 30d. pastes can ask to expire sooner")
+      .takes_value(true)
+      .value_name("duration")
+      .default_value("30d"))
+}
+
+#[cfg(test)]
+mod tests {
+  use handler::PasteServer;
+  use storage::Storage;
+  use storage::tests::temp_directory;
+
+  use lib::{CreatesHtmlUrls, DownloadInfo, Downloads, UploadsSingleFiles};
+  use lib::bins::Hastebin;
+  use lib::config::ConfigHastebin;
+  use lib::files::{Paste, UploadFile};
+
+  use hyper::server::Server;
+
+  use std::fs;
+
+  #[test]
+  fn hastebin_round_trip() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    let server = Server::http("127.0.0.1:0").unwrap();
+    // the hastebin api only gives out keys, so the server's own url does not need the port, which is not known yet
+    let mut listening = server.handle(PasteServer::new(storage, "http://127.0.0.1", 1024)).unwrap();
+    let url = format!("http://{}", listening.socket);
+
+    let hastebin = Hastebin::named("test", &ConfigHastebin { server: Some(url.clone()) });
+    let file = UploadFile::new("hello.txt".to_owned(), b"hello from the test".to_vec());
+    let uploaded = hastebin.upload_single(&file).unwrap();
+    assert!(uploaded.url().starts_with(&url));
+    let id = hastebin.id_from_html_url(uploaded.url()).unwrap();
+    let downloaded = match hastebin.download(&id, &DownloadInfo::default()).unwrap() {
+      Paste::Single(f) => f,
+      Paste::Multiple(fs) => panic!("expected one file, got {}", fs.len())
+    };
+    assert_eq!(downloaded.content, file.content);
+
+    listening.close().unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
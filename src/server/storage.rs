@@ -0,0 +1,239 @@
+use lib::error::*;
+use lib::files::UploadFile;
+
+use rand::{Rng, thread_rng};
+use serde_json;
+use time;
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// A paste as it is saved on disk.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredPaste {
+  /// When the paste was created, in seconds since the Unix epoch.
+  pub created: i64,
+  /// When the paste expires, in seconds since the Unix epoch.
+  pub expires: i64,
+  pub deletion_key: String,
+  pub files: Vec<UploadFile>
+}
+
+impl StoredPaste {
+  pub fn is_expired(&self) -> bool {
+    self.expires <= time::get_time().sec
+  }
+}
+
+/// Pastes saved as one JSON file each in a directory.
+#[derive(Clone)]
+pub struct Storage {
+  directory: PathBuf,
+  expire: u64
+}
+
+impl Storage {
+  /// Opens the storage in `directory`, creating it if needed. Pastes expire after `expire` seconds, unless they ask to
+  /// expire sooner.
+  pub fn new<P: AsRef<Path>>(directory: P, expire: u64) -> Result<Storage> {
+    let directory = directory.as_ref().to_owned();
+    fs::create_dir_all(&directory).chain_err(|| format!("could not create {}", directory.to_string_lossy()))?;
+    Ok(Storage {
+      directory: directory,
+      expire: expire
+    })
+  }
+
+  pub fn create(&self, files: Vec<UploadFile>, expires_in: Option<u64>) -> Result<(String, StoredPaste)> {
+    let now = time::get_time().sec;
+    let expires_in = expires_in.map(|e| e.min(self.expire)).unwrap_or(self.expire);
+    let paste = StoredPaste {
+      created: now,
+      expires: now + expires_in as i64,
+      deletion_key: thread_rng().gen_ascii_chars().take(32).collect(),
+      files: files
+    };
+    let content = serde_json::to_vec(&paste)?;
+    loop {
+      let id: String = thread_rng().gen_ascii_chars().take(10).collect();
+      let path = self.path(&id);
+      if path.exists() {
+        continue;
+      }
+      // the paste is written to a temporary file and renamed into place, so a paste is never read half-written
+      let temp_path = self.directory.join(format!("{}.json.tmp", id));
+      // create_new fails if the file exists, so two uploads never write to the same file
+      let mut f = match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+        Ok(f) => f,
+        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+        Err(e) => return Err(e).chain_err(|| format!("could not create {}", temp_path.to_string_lossy()))
+      };
+      let written = f.write_all(&content)
+        .and_then(|_| f.sync_all())
+        .and_then(|_| fs::rename(&temp_path, &path));
+      if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e).chain_err(|| format!("could not write {}", path.to_string_lossy()));
+      }
+      debug!("stored paste {}", id);
+      return Ok((id, paste));
+    }
+  }
+
+  /// The paste with the given ID, or `None` if it does not exist or has expired. Expired pastes are deleted.
+  pub fn get(&self, id: &str) -> Result<Option<StoredPaste>> {
+    if !is_id(id) {
+      return Ok(None);
+    }
+    let path = self.path(id);
+    let mut f = match File::open(&path) {
+      Ok(f) => f,
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+      Err(e) => return Err(e).chain_err(|| format!("could not open {}", path.to_string_lossy()))
+    };
+    let mut content = Vec::new();
+    f.read_to_end(&mut content)?;
+    let paste: StoredPaste = serde_json::from_slice(&content).chain_err(|| format!("invalid paste in {}", path.to_string_lossy()))?;
+    if paste.is_expired() {
+      debug!("paste {} has expired", id);
+      self.delete(id)?;
+      return Ok(None);
+    }
+    Ok(Some(paste))
+  }
+
+  /// Deletes a paste, returning whether it existed.
+  pub fn delete(&self, id: &str) -> Result<bool> {
+    if !is_id(id) {
+      return Ok(false);
+    }
+    match fs::remove_file(self.path(id)) {
+      Ok(_) => Ok(true),
+      Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+      Err(e) => Err(e.into())
+    }
+  }
+
+  /// Deletes every expired paste, returning how many were deleted.
+  pub fn sweep(&self) -> Result<usize> {
+    let mut deleted = 0;
+    for entry in fs::read_dir(&self.directory)? {
+      let path = entry?.path();
+      let id = match path.file_stem().and_then(|x| x.to_str()) {
+        Some(i) if is_id(i) && path.extension().map(|x| x == "json").unwrap_or(false) => i.to_owned(),
+        _ => continue
+      };
+      // get deletes the paste if it has expired
+      match self.get(&id) {
+        Ok(None) => deleted += 1,
+        Ok(Some(_)) => {},
+        Err(e) => warn!("could not check paste {}: {}", id, e)
+      }
+    }
+    Ok(deleted)
+  }
+
+  fn path(&self, id: &str) -> PathBuf {
+    self.directory.join(format!("{}.json", id))
+  }
+}
+
+/// Paste IDs are ASCII letters and digits, so they are safe to use as file names.
+pub fn is_id(id: &str) -> bool {
+  !id.is_empty() && id.chars().all(|c| match c {
+    'a'...'z' | 'A'...'Z' | '0'...'9' => true,
+    _ => false
+  })
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  use rand::{Rng, thread_rng};
+
+  use std::env;
+  use std::fs;
+  use std::path::PathBuf;
+
+  /// A directory under the system's temporary directory that no other test uses.
+  pub fn temp_directory() -> PathBuf {
+    let name: String = thread_rng().gen_ascii_chars().take(10).collect();
+    env::temp_dir().join(format!("bins-server-test-{}", name))
+  }
+
+  fn hello() -> Vec<UploadFile> {
+    vec![UploadFile::new("hello.txt".to_owned(), b"hello".to_vec())]
+  }
+
+  #[test]
+  fn create_and_get() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    let (id, created) = storage.create(hello(), None).unwrap();
+    assert!(is_id(&id));
+    assert_eq!(created.expires - created.created, 60);
+    let paste = storage.get(&id).unwrap().unwrap();
+    assert_eq!(paste.deletion_key, created.deletion_key);
+    assert_eq!(paste.files.len(), 1);
+    assert_eq!(paste.files[0].name, "hello.txt");
+    assert_eq!(paste.files[0].content, b"hello");
+    // only the paste itself is left, not the temporary file it was written to
+    let names: Vec<String> = fs::read_dir(&directory).unwrap()
+      .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+      .collect();
+    assert_eq!(names, vec![format!("{}.json", id)]);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn expires_in_is_capped() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    let (_, paste) = storage.create(hello(), Some(3600)).unwrap();
+    assert_eq!(paste.expires - paste.created, 60);
+    let (_, paste) = storage.create(hello(), Some(30)).unwrap();
+    assert_eq!(paste.expires - paste.created, 30);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn get_after_expiry() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    let (id, _) = storage.create(hello(), Some(0)).unwrap();
+    assert!(storage.get(&id).unwrap().is_none());
+    // getting an expired paste deletes it
+    assert!(!storage.path(&id).exists());
+    assert!(!storage.delete(&id).unwrap());
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn get_and_delete_invalid_ids() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    assert!(storage.get("../escape").unwrap().is_none());
+    assert!(storage.get("").unwrap().is_none());
+    assert!(!storage.delete("../escape").unwrap());
+    assert!(!storage.delete("missing").unwrap());
+    fs::remove_dir_all(&directory).unwrap();
+  }
+
+  #[test]
+  fn sweep() {
+    let directory = temp_directory();
+    let storage = Storage::new(&directory, 60).unwrap();
+    let (kept, _) = storage.create(hello(), None).unwrap();
+    storage.create(hello(), Some(0)).unwrap();
+    storage.create(hello(), Some(0)).unwrap();
+    // files that are not pastes are left alone
+    File::create(directory.join("notes.txt")).unwrap();
+    assert_eq!(storage.sweep().unwrap(), 2);
+    assert!(storage.get(&kept).unwrap().is_some());
+    assert!(directory.join("notes.txt").exists());
+    assert_eq!(storage.sweep().unwrap(), 0);
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
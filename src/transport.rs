@@ -0,0 +1,316 @@
+//! How bins send HTTP requests.
+//!
+//! Bins make requests through an `HttpClient`, which hands them to a `Transport`. Normally the transport is a hyper
+//! `Client`, but a `Recorder` can be used to save every request and response to a fixture file, and a `Replay` can
+//! answer requests from that file without using the network.
+
+use error::*;
+
+use hyper::Client;
+use hyper::client::IntoUrl;
+use hyper::header::{Header, HeaderFormat, Headers};
+use hyper::http::RawStatus;
+use hyper::method::Method;
+use hyper::net::HttpsConnector;
+use hyper::status::StatusCode;
+use url::Url;
+use serde_json;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Something that can send HTTP requests.
+pub trait Transport: Send + Sync {
+  fn send(&self, request: Request) -> Result<Response>;
+}
+
+#[derive(Debug)]
+pub struct Request {
+  pub method: Method,
+  pub url: Url,
+  pub headers: Headers,
+  pub body: Vec<u8>
+}
+
+/// A response whose body has already been read.
+#[derive(Debug)]
+pub struct Response {
+  pub status: StatusCode,
+  pub headers: Headers,
+  pub url: Url,
+  status_raw: RawStatus,
+  body: Cursor<Vec<u8>>
+}
+
+impl Response {
+  pub fn new(url: Url, status: RawStatus, headers: Headers, body: Vec<u8>) -> Response {
+    Response {
+      status: StatusCode::from_u16(status.0),
+      headers: headers,
+      url: url,
+      status_raw: status,
+      body: Cursor::new(body)
+    }
+  }
+
+  pub fn status_raw(&self) -> &RawStatus {
+    &self.status_raw
+  }
+}
+
+impl Read for Response {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    self.body.read(buf)
+  }
+}
+
+impl Transport for Client {
+  fn send(&self, request: Request) -> Result<Response> {
+    let mut res = self.request(request.method, request.url)
+      .headers(request.headers)
+      .body(&request.body[..])
+      .send()?;
+    let mut body = Vec::new();
+    res.read_to_end(&mut body)?;
+    Ok(Response::new(res.url.clone(), res.status_raw().clone(), res.headers.clone(), body))
+  }
+}
+
+/// A cheaply cloned handle to a `Transport`, with the same request building methods as a hyper `Client`.
+#[derive(Clone)]
+pub struct HttpClient {
+  transport: Arc<Transport>
+}
+
+impl HttpClient {
+  pub fn new<T: Transport + 'static>(transport: T) -> HttpClient {
+    HttpClient {
+      transport: Arc::new(transport)
+    }
+  }
+
+  pub fn request<U: IntoUrl>(&self, method: Method, url: U) -> RequestBuilder {
+    RequestBuilder {
+      client: self,
+      method: method,
+      url: url.into_url().map_err(Error::from),
+      headers: Headers::new(),
+      body: Vec::new()
+    }
+  }
+
+  pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.request(Method::Get, url)
+  }
+
+  pub fn post<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.request(Method::Post, url)
+  }
+
+  pub fn put<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.request(Method::Put, url)
+  }
+
+  pub fn patch<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.request(Method::Patch, url)
+  }
+
+  pub fn delete<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+    self.request(Method::Delete, url)
+  }
+}
+
+/// A client is a transport too, so transports can be layered on top of the one from `new_client`.
+impl Transport for HttpClient {
+  fn send(&self, request: Request) -> Result<Response> {
+    self.transport.send(request)
+  }
+}
+
+pub struct RequestBuilder<'a> {
+  client: &'a HttpClient,
+  method: Method,
+  url: Result<Url>,
+  headers: Headers,
+  body: Vec<u8>
+}
+
+impl<'a> RequestBuilder<'a> {
+  pub fn header<H: Header + HeaderFormat>(mut self, header: H) -> RequestBuilder<'a> {
+    self.headers.set(header);
+    self
+  }
+
+  /// Replaces all headers set so far, like hyper's `RequestBuilder::headers`.
+  pub fn headers(mut self, headers: Headers) -> RequestBuilder<'a> {
+    self.headers = headers;
+    self
+  }
+
+  pub fn body<B: AsRef<[u8]>>(mut self, body: B) -> RequestBuilder<'a> {
+    self.body = body.as_ref().to_vec();
+    self
+  }
+
+  pub fn send(self) -> Result<Response> {
+    let request = Request {
+      method: self.method,
+      url: self.url?,
+      headers: self.headers,
+      body: self.body
+    };
+    debug!("sending {} {}", request.method, request.url);
+    self.client.transport.send(request)
+  }
+}
+
+/// A request and its response, as saved in a fixture file.
+///
+/// Only the method and URL of a request are saved. Headers and bodies can hold API keys and passwords, so they are left
+/// out to keep them from ending up in fixtures.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Exchange {
+  pub method: String,
+  pub url: String,
+  pub status: u16,
+  #[serde(default)]
+  pub headers: BTreeMap<String, String>,
+  #[serde(default, with = "::files::content")]
+  pub body: Vec<u8>
+}
+
+/// A transport that sends requests with another transport and appends each exchange to a fixture file, one JSON object
+/// per line.
+pub struct Recorder<T> {
+  inner: T,
+  path: PathBuf,
+  lock: Mutex<()>
+}
+
+impl<T: Transport> Recorder<T> {
+  pub fn new<P: AsRef<Path>>(inner: T, path: P) -> Recorder<T> {
+    Recorder {
+      inner: inner,
+      path: path.as_ref().to_owned(),
+      lock: Mutex::new(())
+    }
+  }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+  fn send(&self, request: Request) -> Result<Response> {
+    let method = request.method.to_string();
+    let url = request.url.to_string();
+    let mut res = self.inner.send(request)?;
+    let mut body = Vec::new();
+    res.read_to_end(&mut body)?;
+    let exchange = Exchange {
+      method: method,
+      url: url,
+      status: res.status_raw().0,
+      headers: res.headers.iter().map(|h| (h.name().to_owned(), h.value_string())).collect(),
+      body: body
+    };
+    let mut line = serde_json::to_vec(&exchange)?;
+    line.push(b'\n');
+    {
+      let _guard = self.lock.lock().map_err(|_| ErrorKind::Msg("fixture lock was poisoned".into()))?;
+      let mut f = OpenOptions::new().create(true).append(true).open(&self.path)
+        .chain_err(|| format!("could not open fixture file {}", self.path.to_string_lossy()))?;
+      f.write_all(&line)?;
+    }
+    let status = RawStatus(exchange.status, res.status_raw().1.clone());
+    Ok(Response::new(res.url, status, res.headers, exchange.body))
+  }
+}
+
+/// A transport that answers requests from a fixture file made by a `Recorder`, without using the network.
+///
+/// Each exchange is used once, in the order it was recorded, and is matched to requests by method and URL. Requests
+/// without a matching exchange are errors.
+pub struct Replay {
+  exchanges: Mutex<Vec<Option<Exchange>>>
+}
+
+impl Replay {
+  pub fn new(exchanges: Vec<Exchange>) -> Replay {
+    Replay {
+      exchanges: Mutex::new(exchanges.into_iter().map(Some).collect())
+    }
+  }
+
+  pub fn open<P: AsRef<Path>>(path: P) -> Result<Replay> {
+    let path = path.as_ref();
+    let f = File::open(path).chain_err(|| format!("could not open fixture file {}", path.to_string_lossy()))?;
+    let mut exchanges = Vec::new();
+    for line in BufReader::new(f).lines() {
+      let line = line?;
+      if line.trim().is_empty() {
+        continue;
+      }
+      exchanges.push(serde_json::from_str(&line).chain_err(|| format!("invalid fixture in {}", path.to_string_lossy()))?);
+    }
+    Ok(Replay::new(exchanges))
+  }
+}
+
+impl Transport for Replay {
+  fn send(&self, request: Request) -> Result<Response> {
+    let method = request.method.to_string();
+    let url = request.url.to_string();
+    let mut exchanges = self.exchanges.lock().map_err(|_| ErrorKind::Msg("fixture lock was poisoned".into()))?;
+    let found = exchanges.iter_mut()
+      .find(|e| e.as_ref().map(|e| e.method == method && e.url == url).unwrap_or(false))
+      .and_then(|e| e.take());
+    let exchange = match found {
+      Some(e) => e,
+      None => bail!("no fixture left for {} {}", method, url)
+    };
+    let mut headers = Headers::new();
+    for (name, value) in exchange.headers {
+      headers.set_raw(name, vec![value.into_bytes()]);
+    }
+    let reason = StatusCode::from_u16(exchange.status).canonical_reason().unwrap_or("");
+    Ok(Response::new(request.url, RawStatus(exchange.status, Cow::Borrowed(reason)), headers, exchange.body))
+  }
+}
+
+/// Creates the client that bins send requests with.
+///
+/// If `BINS_REPLAY` is set to a fixture file, requests are answered from it instead of the network. If `BINS_RECORD` is
+/// set to a file, every request and response is saved to it.
+pub fn new_client() -> HttpClient {
+  if let Some(path) = env::var_os("BINS_REPLAY") {
+    return match Replay::open(&path) {
+      Ok(r) => HttpClient::new(r),
+      Err(e) => {
+        error!("could not load fixtures: {}", e);
+        for cause in e.iter().skip(1) {
+          error!("{}", cause);
+        }
+        HttpClient::new(Replay::new(Vec::new()))
+      }
+    };
+  }
+  if let Some(path) = env::var_os("BINS_RECORD") {
+    return HttpClient::new(Recorder::new(https_client(), path));
+  }
+  HttpClient::new(https_client())
+}
+
+#[cfg(feature = "openssl")]
+fn https_client() -> Client {
+  use hyper_openssl::OpensslClient;
+  Client::with_connector(HttpsConnector::new(OpensslClient::new().unwrap()))
+}
+
+#[cfg(feature = "rustls")]
+fn https_client() -> Client {
+  use hyper_rustls::TlsClient;
+  Client::with_connector(HttpsConnector::new(TlsClient::new()))
+}
@@ -0,0 +1,61 @@
+//! Parsing of human-friendly durations and sizes, as used in the configuration file and on the command line.
+
+use error::*;
+
+/// Parses a duration such as "90s", "10m", "1 hour" or "2w" into seconds.
+pub fn parse_duration(s: &str) -> Result<u64> {
+  let s = s.trim();
+  let split = s.find(|c: char| !c.is_digit(10)).unwrap_or_else(|| s.len());
+  let (number, unit) = s.split_at(split);
+  let number: u64 = number.parse().chain_err(|| format!("\"{}\" is not a valid duration", s))?;
+  let unit = match unit.trim().to_lowercase().as_str() {
+    "" | "s" | "sec" | "secs" | "second" | "seconds" => 1,
+    "m" | "min" | "mins" | "minute" | "minutes" => 60,
+    "h" | "hour" | "hours" => 60 * 60,
+    "d" | "day" | "days" => 60 * 60 * 24,
+    "w" | "week" | "weeks" => 60 * 60 * 24 * 7,
+    "mo" | "month" | "months" => 60 * 60 * 24 * 30,
+    "y" | "year" | "years" => 60 * 60 * 24 * 365,
+    u => bail!("\"{}\" is not a valid unit of time", u)
+  };
+  if number == 0 {
+    bail!("\"{}\" is not a valid duration", s);
+  }
+  match number.checked_mul(unit) {
+    Some(secs) => Ok(secs),
+    None => bail!("\"{}\" is too long a duration", s)
+  }
+}
+
+/// Parses a size such as "512", "10KiB" or "1.5 MB" into bytes.
+pub fn parse_size(s: &str) -> Result<u64> {
+  let mut size: Vec<char> = Vec::new();
+  let mut unit: Vec<char> = Vec::new();
+  for c in s.trim().chars() {
+    if "0123456789.".contains(c) {
+      if !unit.is_empty() {
+        bail!("\"{}\" is not a valid size", s);
+      }
+      size.push(c);
+    } else if "bBkKmMgGiI".contains(c) {
+      unit.push(c);
+    }
+  }
+  let size: f64 = size.into_iter().collect::<String>().parse().chain_err(|| format!("\"{}\" is not a valid size", s))?;
+  let unit = unit.into_iter().collect::<String>().to_lowercase();
+  let unit = if unit.is_empty() {
+    1
+  } else {
+    match unit.as_str() {
+      "b" => 1,
+      "kb" => (10 as u64).pow(3),
+      "kib" => (2 as u64).pow(10),
+      "mb" => (10 as u64).pow(6),
+      "mib" => (2 as u64).pow(20),
+      "gb" => (10 as u64).pow(9),
+      "gib" => (2 as u64).pow(30),
+      u => bail!("\"{}\" is not a valid unit of size", u)
+    }
+  };
+  Ok((size * unit as f64).round() as u64)
+}
@@ -0,0 +1,197 @@
+//! Bins tested against fixtures recorded from their servers, replayed without using the network.
+
+extern crate bins;
+
+use bins::{CreatesHtmlUrls, CreatesRawUrls, DownloadInfo, Downloads, HasClient, PasteUrl, Uploads};
+use bins::bins::{Bitbucket, Fedora, FileHost, Gist, GitLab, Hastebin, PasteGg, Pastebin};
+use bins::config::{CommandLineOptions, Config, ConfigHastebin};
+use bins::files::{Paste, UploadFile};
+use bins::transport::{HttpClient, Replay};
+
+use std::sync::Arc;
+
+fn replay(fixture: &str) -> HttpClient {
+  let path = format!("{}/tests/fixtures/{}.jsonl", env!("CARGO_MANIFEST_DIR"), fixture);
+  HttpClient::new(Replay::open(&path).unwrap())
+}
+
+fn hello() -> Vec<UploadFile> {
+  vec![UploadFile::new("hello.txt".to_owned(), b"hello".to_vec())]
+}
+
+/// The names and contents of the files in a downloaded paste.
+fn files(paste: Paste) -> Vec<(String, Vec<u8>)> {
+  let files = match paste {
+    Paste::Single(f) => vec![f],
+    Paste::Multiple(fs) => fs
+  };
+  files.into_iter().map(|f| (f.name.name(), f.content)).collect()
+}
+
+#[test]
+fn gist_upload() {
+  let gist = Gist::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("gist_upload"));
+  let files = vec![UploadFile::new("hello.txt".to_owned(), b"hello world".to_vec())];
+  let urls = gist.upload(&files, false).unwrap();
+  assert_eq!(urls.len(), 1);
+  match urls[0] {
+    PasteUrl::Html { ref url, .. } => assert_eq!(url, "https://gist.github.com/aa5a315d61ae9438b18d"),
+    ref u => panic!("expected an html url, got {:?}", u)
+  }
+}
+
+#[test]
+fn hastebin_index() {
+  let hastebin = Hastebin::named("hastebin", &ConfigHastebin::default()).with_client(replay("hastebin_index"));
+  let urls = hastebin.create_raw_url("iqusazetev").unwrap();
+  let found: Vec<(String, &str)> = urls.iter()
+    .map(|u| match *u {
+      PasteUrl::Raw { .. } => (u.name().unwrap().name(), u.url()),
+      ref u => panic!("expected a raw url, got {:?}", u)
+    })
+    .collect();
+  assert_eq!(found, vec![("one.txt".to_owned(), "https://hastebin.com/raw/ucixiyuvoz"),
+                         ("two.txt".to_owned(), "https://hastebin.com/raw/ojojewiqax")]);
+}
+
+#[test]
+fn hastebin_not_index() {
+  let hastebin = Hastebin::named("hastebin", &ConfigHastebin::default()).with_client(replay("hastebin_index"));
+  let urls = hastebin.create_raw_url("ucixiyuvoz").unwrap();
+  assert_eq!(urls.len(), 1);
+  match urls[0] {
+    PasteUrl::Downloaded(ref url, ref file) => {
+      assert_eq!(url, "https://hastebin.com/raw/ucixiyuvoz");
+      assert_eq!(file.content, b"not an index");
+    },
+    ref u => panic!("expected a downloaded file, got {:?}", u)
+  }
+}
+
+#[test]
+fn bitbucket_id_from_raw_url() {
+  let bitbucket = Bitbucket::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()));
+  assert_eq!(bitbucket.id_from_raw_url("https://bitbucket.org/!api/2.0/snippets/someone/6Ryd8/files/hello.txt"),
+             Some("someone/6Ryd8".to_owned()));
+  assert_eq!(bitbucket.id_from_raw_url("https://bitbucket.org/snippets/someone/6Ryd8#file-hello.txt"),
+             Some("someone/6Ryd8".to_owned()));
+  assert_eq!(bitbucket.id_from_raw_url("https://bitbucket.org/!api/2.0"), None);
+  assert_eq!(bitbucket.id_from_raw_url("not a url"), None);
+}
+
+#[test]
+fn pastegg_upload() {
+  let pastegg = PasteGg::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("pastegg_upload"));
+  let urls = pastegg.upload(&hello(), false).unwrap();
+  assert_eq!(urls.len(), 1);
+  assert_eq!(urls[0].url(), "https://paste.gg/9c51f4c3b4a74f6b8a1d2e3f4a5b6c7d");
+  assert_eq!(urls[0].deletion_key(), Some("Zr3x7WqPb2"));
+}
+
+#[test]
+fn pastegg_download() {
+  let pastegg = PasteGg::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("pastegg_download"));
+  let id = pastegg.id_from_html_url("https://paste.gg/p/anonymous/9c51f4c3b4a74f6b8a1d2e3f4a5b6c7d").unwrap();
+  let paste = pastegg.download(&id, &DownloadInfo::default()).unwrap();
+  assert_eq!(files(paste), vec![("hello.txt".to_owned(), b"hello from paste.gg".to_vec())]);
+}
+
+fn pastebin_config() -> Config {
+  let mut config = Config::default();
+  config.pastebin.api_key = Some("dev-key".to_owned());
+  config.pastebin.username = Some("someone".to_owned());
+  config.pastebin.password = Some("hunter2".to_owned());
+  config
+}
+
+#[test]
+fn pastebin_upload() {
+  let pastebin = Pastebin::new(Arc::new(pastebin_config()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("pastebin_upload"));
+  let urls = pastebin.upload(&hello(), false).unwrap();
+  assert_eq!(urls.len(), 1);
+  assert_eq!(urls[0].url(), "https://pastebin.com/Xm3kQ9Lp");
+}
+
+#[test]
+fn pastebin_bad_key() {
+  let pastebin = Pastebin::new(Arc::new(pastebin_config()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("pastebin_bad_key"));
+  let err = pastebin.upload(&hello(), false).unwrap_err();
+  assert_eq!(err.to_string(), "Bad API request, invalid api_dev_key");
+}
+
+#[test]
+fn pastebin_bad_login() {
+  let mut options = CommandLineOptions::default();
+  options.authed = Some(true);
+  // the fixture has no upload, so this fails if the upload is sent without logging in
+  let pastebin = Pastebin::new(Arc::new(pastebin_config()), Arc::new(options))
+    .with_client(replay("pastebin_bad_login"));
+  let err = pastebin.upload(&hello(), false).unwrap_err();
+  assert_eq!(err.to_string(), "Bad API request, invalid login");
+}
+
+fn gitlab() -> GitLab {
+  let mut config = Config::default();
+  config.gitlab.token = Some("glpat-token".to_owned());
+  GitLab::new(Arc::new(config), Arc::new(CommandLineOptions::default()))
+}
+
+#[test]
+fn gitlab_upload() {
+  let gitlab = gitlab().with_client(replay("gitlab_upload"));
+  let urls = gitlab.upload(&hello(), false).unwrap();
+  assert_eq!(urls.len(), 1);
+  assert_eq!(urls[0].url(), "https://gitlab.com/-/snippets/1758092");
+}
+
+#[test]
+fn gitlab_download() {
+  let gitlab = gitlab().with_client(replay("gitlab_download"));
+  let id = gitlab.id_from_html_url("https://gitlab.com/-/snippets/1758092").unwrap();
+  let mut downloaded = files(gitlab.download(&id, &DownloadInfo::default()).unwrap());
+  downloaded.sort();
+  assert_eq!(downloaded, vec![("hello.txt".to_owned(), b"hello from gitlab".to_vec()),
+                              ("other file.txt".to_owned(), b"another file".to_vec())]);
+}
+
+#[test]
+fn fedora_upload() {
+  let fedora = Fedora::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("fedora_upload"));
+  let urls = fedora.upload(&hello(), false).unwrap();
+  assert_eq!(urls.len(), 1);
+  assert_eq!(urls[0].url(), "https://paste.fedoraproject.org/paste/qB7zD2kL9mN4");
+}
+
+#[test]
+fn fedora_download() {
+  let fedora = Fedora::new(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("fedora_download"));
+  let id = fedora.id_from_html_url("https://paste.fedoraproject.org/paste/qB7zD2kL9mN4").unwrap();
+  let paste = fedora.download(&id, &DownloadInfo::default()).unwrap();
+  assert_eq!(files(paste), vec![("qB7zD2kL9mN4".to_owned(), b"hello from fedora".to_vec())]);
+}
+
+#[test]
+fn null_pointer_upload() {
+  let null_pointer = FileHost::null_pointer(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("null_pointer_upload"));
+  let urls = null_pointer.upload(&hello(), false).unwrap();
+  assert_eq!(urls.len(), 1);
+  assert_eq!(urls[0].url(), "https://0x0.st/oQ2a.txt");
+  assert_eq!(urls[0].deletion_key(), Some("Kd8sP0aQz"));
+}
+
+#[test]
+fn null_pointer_download() {
+  let null_pointer = FileHost::null_pointer(Arc::new(Config::default()), Arc::new(CommandLineOptions::default()))
+    .with_client(replay("null_pointer_download"));
+  let id = null_pointer.id_from_html_url("https://0x0.st/oQ2a.txt").unwrap();
+  let paste = null_pointer.download(&id, &DownloadInfo::default()).unwrap();
+  assert_eq!(files(paste), vec![("oQ2a.txt".to_owned(), b"hello from 0x0".to_vec())]);
+}